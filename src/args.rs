@@ -1,15 +1,47 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use pngrs::{
+    ChunkPlacement, ChunkType, Cipher, Compression, Limits, RenderingIntent, StripOptions,
+};
+
+use crate::commands::{ColorOptions, TrailingOptions};
 
 #[derive(Parser)]
 #[command(name = "PNGrs")]
 #[command(author = "Mauro Sambartolomeo")]
 #[command(version = "1.0")]
 #[command(about = "Encode and decode messages into a PNG files")]
+#[command(
+    after_help = "Use - as an image, message or output path to read from stdin or write to stdout"
+)]
 pub struct Args {
     #[command(subcommand)]
     pub command: Commands,
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+#[derive(clap::Args)]
+pub struct LimitArgs {
+    #[arg(long, global = true, help = "Largest data length allowed for a chunk")]
+    max_chunk_length: Option<u32>,
+    #[arg(
+        long,
+        global = true,
+        help = "Largest number of chunks allowed in an image"
+    )]
+    max_chunks: Option<usize>,
+    #[arg(
+        long,
+        global = true,
+        help = "Largest size in bytes allowed for inflated data"
+    )]
+    max_decompressed_size: Option<usize>,
+    #[arg(long, global = true, help = "Largest width allowed for an image")]
+    max_width: Option<u32>,
+    #[arg(long, global = true, help = "Largest height allowed for an image")]
+    max_height: Option<u32>,
 }
 
 #[derive(Subcommand)]
@@ -18,16 +50,415 @@ pub enum Commands {
     Encode {
         path: PathBuf,
         code: String,
-        message: String,
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
         output: Option<PathBuf>,
+        #[command(flatten)]
+        options: EncodeArgs,
     },
 
     #[command(about = "Decode a message stored in a PNG file")]
-    Decode { path: PathBuf, code: String },
+    Decode {
+        path: PathBuf,
+        code: String,
+        #[arg(
+            long,
+            conflicts_with = "index",
+            help = "Decode every message with this code"
+        )]
+        all: bool,
+        #[arg(
+            long,
+            help = "Decode the message at this position among the ones with this code, from 0"
+        )]
+        index: Option<usize>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Decrypt messages encrypted to recipients with the secret keys in this file"
+        )]
+        identity: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with = "all",
+            help = "Write the message to this file instead of printing it"
+        )]
+        out: Option<PathBuf>,
+    },
 
     #[command(about = "Remove a message from a PNG file")]
-    Remove { path: PathBuf, code: String },
+    Remove {
+        path: PathBuf,
+        code: String,
+        #[arg(
+            long,
+            conflicts_with = "index",
+            help = "Remove every message with this code"
+        )]
+        all: bool,
+        #[arg(
+            long,
+            help = "Remove the message at this position among the ones with this code, from 0"
+        )]
+        index: Option<usize>,
+    },
 
     #[command(about = "Print a list of PNG chunks that can be searched for messages")]
     Print { path: PathBuf },
+
+    #[command(about = "Set a text entry with the given keyword in a PNG file")]
+    SetText {
+        path: PathBuf,
+        keyword: String,
+        text: String,
+        output: Option<PathBuf>,
+        #[arg(long, help = "Compress the text with zlib")]
+        compress: bool,
+        #[arg(long, help = "Store the text as UTF-8 in an iTXt chunk")]
+        international: bool,
+        #[arg(long, help = "Language tag of an iTXt chunk")]
+        language: Option<String>,
+        #[arg(long, help = "Keyword translated to the language of an iTXt chunk")]
+        translated_keyword: Option<String>,
+    },
+
+    #[command(
+        about = "Print the text entries of a PNG file, or only the one with the given keyword"
+    )]
+    GetText {
+        path: PathBuf,
+        keyword: Option<String>,
+    },
+
+    #[command(about = "Remove the text entries with the given keyword from a PNG file")]
+    RemoveText { path: PathBuf, keyword: String },
+
+    #[command(about = "Print the frames of an animated PNG file")]
+    Frames { path: PathBuf },
+
+    #[command(
+        about = "Check that the chunks of a PNG file follow the ordering rules of the specification"
+    )]
+    Check { path: PathBuf },
+
+    #[command(about = "Remove every chunk not needed to display a PNG file")]
+    Strip {
+        path: PathBuf,
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        options: StripArgs,
+    },
+
+    #[command(about = "List the EXIF tags of a PNG file or remove some of them")]
+    Exif {
+        path: PathBuf,
+        output: Option<PathBuf>,
+        #[arg(long, value_name = "TAG", help = "Remove the EXIF tag with this name")]
+        remove: Vec<String>,
+        #[arg(long, help = "Remove every GPS tag")]
+        remove_gps: bool,
+        #[arg(long, help = "Remove the thumbnail and its tags")]
+        remove_thumbnail: bool,
+    },
+
+    #[command(about = "Show, extract or replace the color management chunks of a PNG file")]
+    Color {
+        path: PathBuf,
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        options: ColorArgs,
+    },
+
+    #[command(about = "Show, extract, strip or set the data stored after the end of a PNG file")]
+    Trailing {
+        path: PathBuf,
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        options: TrailingArgs,
+    },
+
+    #[command(about = "Recover the readable chunks of a damaged PNG file")]
+    Salvage {
+        path: PathBuf,
+        #[arg(help = "Write the recovered chunks to this file")]
+        output: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Print the message stored with this code if it was recovered"
+        )]
+        code: Option<String>,
+    },
+
+    #[command(about = "Generate a key pair to encrypt messages to with --recipient or sign them")]
+    Keygen {
+        #[arg(help = "Write the secret key to this file")]
+        secret: PathBuf,
+        #[arg(help = "Write the public key to this file")]
+        public: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Generate a key pair to sign messages with --sign instead"
+        )]
+        signing: bool,
+    },
+
+    #[command(about = "Verify the signatures of the messages in a PNG file")]
+    VerifySig {
+        path: PathBuf,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Only trust signatures made by the public keys in this file"
+        )]
+        trusted: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Placement {
+    BeforeIend,
+    BeforeFirstIdat,
+    AfterIhdr,
+    AfterLastIdat,
+}
+
+#[derive(clap::Args)]
+pub struct EncodeArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Encode the contents of this file instead of a message"
+    )]
+    pub file: Option<PathBuf>,
+    #[arg(
+        short,
+        long = "output",
+        value_name = "FILE",
+        conflicts_with = "output",
+        help = "Write the image to this file, the way to set the output along with --file"
+    )]
+    pub output_file: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Placement::BeforeIend)]
+    pub placement: Placement,
+    #[arg(
+        long,
+        help = "Encode even if the code is critical, public or has the reserved bit set"
+    )]
+    pub force: bool,
+    #[arg(
+        long,
+        help = "Encrypt the message with a passphrase, read from PNGRS_PASSPHRASE or prompted"
+    )]
+    pub encrypt: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "encrypt",
+        help = "Encrypt the message to the public keys in this file, can be repeated"
+    )]
+    pub recipient: Vec<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Algorithm::Chacha20Poly1305, requires = "encrypt")]
+    pub cipher: Algorithm,
+    #[arg(
+        long,
+        value_enum,
+        help = "Compress the message, it is stored uncompressed if it does not get smaller"
+    )]
+    pub compress: Option<Codec>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Sign the message with the secret key in this file"
+    )]
+    pub sign: Option<PathBuf>,
+    #[arg(
+        long,
+        requires = "sign",
+        help = "Bind the signature to the pixels, so later changes to them are detected"
+    )]
+    pub bind_image: bool,
+}
+
+#[derive(clap::Args)]
+pub struct StripArgs {
+    #[arg(long, value_name = "CODE", help = "Also keep chunks of this type")]
+    allow: Vec<ChunkType>,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Drop chunks of this type even if they affect rendering"
+    )]
+    deny: Vec<ChunkType>,
+    #[arg(
+        long,
+        help = "Keep private and unknown critical chunks, such as encoded messages"
+    )]
+    keep_messages: bool,
+}
+
+#[derive(clap::Args)]
+pub struct ColorArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the embedded ICC profile to this file"
+    )]
+    extract_icc: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "srgb",
+        help = "Embed this ICC profile, replacing any sRGB chunk"
+    )]
+    icc: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value = "ICC profile",
+        help = "Name of the embedded ICC profile"
+    )]
+    icc_name: String,
+    #[arg(
+        long,
+        value_enum,
+        help = "Mark the image as sRGB with this rendering intent, replacing any ICC profile"
+    )]
+    srgb: Option<Intent>,
+    #[arg(long, help = "Set the image gamma times 100000, 45455 for 1/2.2")]
+    gamma: Option<u32>,
+}
+
+#[derive(clap::Args)]
+pub struct TrailingArgs {
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the trailing data to this file"
+    )]
+    extract: Option<PathBuf>,
+    #[arg(long, conflicts_with = "set", help = "Remove the trailing data")]
+    strip: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Replace the trailing data with the contents of this file"
+    )]
+    set: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with_all = ["strip", "set"],
+        help = "Move chunks stored after IEND, as version 1.0 did with messages, into the image"
+    )]
+    adopt: bool,
+}
+
+impl From<TrailingArgs> for TrailingOptions {
+    fn from(value: TrailingArgs) -> Self {
+        Self {
+            extract: value.extract,
+            strip: value.strip,
+            set: value.set,
+            adopt: value.adopt,
+        }
+    }
+}
+
+impl From<ColorArgs> for ColorOptions {
+    fn from(value: ColorArgs) -> Self {
+        Self {
+            extract_icc: value.extract_icc,
+            icc: value.icc,
+            icc_name: value.icc_name,
+            srgb: value.srgb.map(Into::into),
+            gamma: value.gamma,
+        }
+    }
+}
+
+impl From<StripArgs> for StripOptions {
+    fn from(value: StripArgs) -> Self {
+        Self {
+            allow: value.allow.into_iter().collect(),
+            deny: value.deny.into_iter().collect(),
+            keep_messages: value.keep_messages,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Algorithm {
+    #[value(name = "chacha20poly1305")]
+    Chacha20Poly1305,
+    #[value(name = "aes256gcm")]
+    Aes256Gcm,
+}
+
+impl From<Algorithm> for Cipher {
+    fn from(value: Algorithm) -> Self {
+        match value {
+            Algorithm::Chacha20Poly1305 => Self::ChaCha20Poly1305,
+            Algorithm::Aes256Gcm => Self::Aes256Gcm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Codec {
+    Deflate,
+    Zstd,
+}
+
+impl From<Codec> for Compression {
+    fn from(value: Codec) -> Self {
+        match value {
+            Codec::Deflate => Self::Deflate,
+            Codec::Zstd => Self::Zstd,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Intent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+impl From<Intent> for RenderingIntent {
+    fn from(value: Intent) -> Self {
+        match value {
+            Intent::Perceptual => Self::Perceptual,
+            Intent::RelativeColorimetric => Self::RelativeColorimetric,
+            Intent::Saturation => Self::Saturation,
+            Intent::AbsoluteColorimetric => Self::AbsoluteColorimetric,
+        }
+    }
+}
+
+impl From<LimitArgs> for Limits {
+    fn from(value: LimitArgs) -> Self {
+        let default = Self::default();
+
+        Self {
+            max_chunk_length: value.max_chunk_length.unwrap_or(default.max_chunk_length),
+            max_chunk_count: value.max_chunks.unwrap_or(default.max_chunk_count),
+            max_decompressed_size: value
+                .max_decompressed_size
+                .unwrap_or(default.max_decompressed_size),
+            max_width: value.max_width.unwrap_or(default.max_width),
+            max_height: value.max_height.unwrap_or(default.max_height),
+        }
+    }
+}
+
+impl From<Placement> for ChunkPlacement {
+    fn from(value: Placement) -> Self {
+        match value {
+            Placement::BeforeIend => Self::BeforeIend,
+            Placement::BeforeFirstIdat => Self::BeforeFirstIdat,
+            Placement::AfterIhdr => Self::AfterIhdr,
+            Placement::AfterLastIdat => Self::AfterLastIdat,
+        }
+    }
 }
@@ -1,61 +1,451 @@
-use std::fs;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::{bail, Result};
 
-use pngrs::{Chunk, ChunkType, Png};
+use pngrs::{
+    decrypt, decrypt_with_identities, encrypt, encrypt_to_recipients, is_encrypted,
+    is_encrypted_to_recipients, preview_message, Chunk, ChunkRef, ChunkStatus, ChunkType, Gamma,
+    IccProfile, Identity, Ifd, KdfParams, Limits, MessageSignature, Payload, Png, PngReader,
+    PngRef, Recipient, RenderingIntent, SigningKey, Srgb, StripOptions, TextChunk, VerifyingKey,
+};
 
-pub fn encode(path: PathBuf, code: &str, message: String, output: Option<PathBuf>) -> Result<()> {
-    let mut png = Png::from_file(&path)?;
+use crate::args::EncodeArgs;
+
+const PASSPHRASE_VARIABLE: &str = "PNGRS_PASSPHRASE";
+/// Path that stands for standard input or output instead of a file
+const STDIO: &str = "-";
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// Opens a file for reading, or standard input for `-`
+fn open_input(path: &Path) -> Result<Box<dyn BufRead>> {
+    if is_stdio(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    Ok(Box::new(BufReader::new(File::open(path)?)))
+}
+
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open_input(path)?.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+/// Writes `data` to a file, or to standard output for `-`
+fn write_output(path: &Path, data: &[u8]) -> Result<()> {
+    if is_stdio(path) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(data)?;
+        stdout.flush()?;
+    } else {
+        fs::write(path, data)?;
+    }
+
+    Ok(())
+}
+
+fn read_png(path: &Path, limits: Limits) -> Result<Png> {
+    Png::from_reader_with_limits(open_input(path)?, limits)
+}
+
+fn write_png(png: &Png, path: &Path) -> Result<()> {
+    if is_stdio(path) {
+        return png.write_to(io::stdout().lock());
+    }
+
+    png.write_to(BufWriter::new(File::create(path)?))
+}
+
+pub fn encode(
+    path: PathBuf,
+    code: &str,
+    message: Option<String>,
+    output: Option<PathBuf>,
+    options: &EncodeArgs,
+    limits: Limits,
+) -> Result<()> {
+    let mut png = read_png(&path, limits)?;
+
+    // NOTE: malformed covers are refused before anything is embedded in them
+    png.ihdr()?;
 
     let chunk_type = ChunkType::from_str(code)?;
 
-    let chunk = Chunk::new(chunk_type, message.into_bytes());
+    check_message_code(&chunk_type, options.force)?;
 
-    png.append_chunk(chunk);
+    let mut payload = match (&options.file, message) {
+        (Some(_), Some(_)) => bail!("A message can not be encoded along with --file"),
+        (Some(file), None) if is_stdio(file) => Payload::binary(read_stdin_once(&path)?),
+        (Some(file), None) => Payload::from_file(file)?,
+        (None, Some(message)) if message == STDIO => {
+            match String::from_utf8(read_stdin_once(&path)?) {
+                Ok(text) => Payload::text(text),
+                Err(error) => Payload::binary(error.into_bytes()),
+            }
+        }
+        (None, Some(message)) => Payload::text(message),
+        (None, None) => bail!("A message or --file is needed"),
+    };
 
-    let out_path = output.unwrap_or(path);
+    if let Some(compress) = options.compress {
+        payload.set_compression(compress.into());
+    }
+    let message = payload.to_bytes()?;
+
+    let data = if !options.recipient.is_empty() {
+        let mut recipients = Vec::new();
+        for path in &options.recipient {
+            recipients.extend(read_keys::<Recipient>(path)?);
+        }
+
+        encrypt_to_recipients(&message, &recipients)?
+    } else if options.encrypt {
+        let passphrase = read_passphrase(true)?;
+        encrypt(
+            &message,
+            passphrase.as_bytes(),
+            options.cipher.into(),
+            &KdfParams::default(),
+        )?
+    } else {
+        message
+    };
+
+    // NOTE: checked before building the chunk, as larger images could not be read back
+    limits.check_chunk_length(u32::try_from(data.len()).unwrap_or(u32::MAX))?;
+    let chunk = Chunk::new(chunk_type, data);
+
+    if let Some(sign) = &options.sign {
+        let [key] = read_keys::<SigningKey>(sign)?
+            .try_into()
+            .map_err(|keys: Vec<_>| {
+                anyhow::anyhow!(
+                    "Expected one signing key in {} but found {}",
+                    sign.display(),
+                    keys.len()
+                )
+            })?;
+        let image_hash = options.bind_image.then(|| png.image_hash());
+
+        let signature = MessageSignature::sign(&key, &chunk, image_hash);
+        png.insert_chunk(signature.to_chunk(), options.placement.into());
+    }
 
-    let out_bytes = png.as_bytes();
+    png.insert_chunk(chunk, options.placement.into());
 
-    fs::write(out_path, out_bytes)?;
+    let out_path = output
+        .or_else(|| options.output_file.clone())
+        .unwrap_or(path);
+
+    write_png(&png, &out_path)?;
 
     Ok(())
 }
 
-pub fn decode(path: &Path, code: &str) -> Result<()> {
-    let png = Png::from_file(path)?;
+/// Passphrase from the `PNGRS_PASSPHRASE` variable, or asked on the terminal when it is not set
+fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VARIABLE) {
+        if passphrase.is_empty() {
+            bail!("The passphrase in {PASSPHRASE_VARIABLE} can not be empty")
+        }
+        return Ok(passphrase);
+    }
 
-    let Some(chunk) = png.chunk_by_type(code) else {
-        bail!("Could not find message encoded with code {code}")
+    let Ok(passphrase) = rpassword::prompt_password("Passphrase: ") else {
+        bail!("Could not read the passphrase, set {PASSPHRASE_VARIABLE} when there is no terminal")
     };
 
-    let message = chunk.data_as_string()?;
+    if passphrase.is_empty() {
+        bail!("The passphrase can not be empty")
+    }
+    if confirm && rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+        bail!("The passphrases do not match")
+    }
+
+    Ok(passphrase)
+}
 
-    println!("The encoded message with code {code} is {message}");
+/// Keys in a key file, one per line, skipping blank lines and comments starting with #
+fn read_keys<T: FromStr<Err = anyhow::Error>>(path: &Path) -> Result<Vec<T>> {
+    let keys = fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(T::from_str)
+        .collect::<Result<Vec<_>>>()?;
+
+    if keys.is_empty() {
+        bail!("No keys found in {}", path.display())
+    }
+
+    Ok(keys)
+}
+
+/// Payload of the message stored in `chunk`, asking for the passphrase the first time one is
+/// encrypted
+fn read_payload(
+    chunk: &Chunk,
+    passphrase: &mut Option<String>,
+    identities: &[Identity],
+    limits: &Limits,
+) -> Result<Payload> {
+    if is_encrypted_to_recipients(chunk.data()) {
+        if identities.is_empty() {
+            bail!("The message is encrypted to recipients, decrypt it with --identity")
+        }
+
+        return Payload::parse(&decrypt_with_identities(chunk.data(), identities)?, limits);
+    }
+
+    if !is_encrypted(chunk.data()) {
+        return Payload::parse(chunk.data(), limits);
+    }
+
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => passphrase.insert(read_passphrase(false)?),
+    };
+
+    Payload::parse(&decrypt(chunk.data(), passphrase.as_bytes())?, limits)
+}
+
+/// Reads the message from standard input, unless the image is already read from it
+fn read_stdin_once(image: &Path) -> Result<Vec<u8>> {
+    if is_stdio(image) {
+        bail!("The image and the message can not both be read from stdin")
+    }
+
+    read_input(Path::new(STDIO))
+}
+
+/// Refuses codes that make viewers reject the image, unless forced, and warns about public ones
+fn check_message_code(chunk_type: &ChunkType, force: bool) -> Result<()> {
+    let problem = if chunk_type.is_critical() {
+        Some("is critical, standard viewers will refuse to open the image")
+    } else if !chunk_type.is_reserved_bit_valid() {
+        Some("has the reserved bit set, it is not a valid chunk type")
+    } else {
+        None
+    };
+
+    match problem {
+        Some(problem) if !force => {
+            bail!(
+                "Code {chunk_type} {problem}, use a code like {} or --force",
+                suggested_code(chunk_type)
+            )
+        }
+        Some(problem) => eprintln!("Warning: code {chunk_type} {problem}"),
+        None => {}
+    }
+
+    if chunk_type.is_public() {
+        eprintln!("Warning: code {chunk_type} is public and could clash with a standard chunk");
+    }
 
     Ok(())
 }
 
-pub fn remove(path: &Path, code: &str) -> Result<()> {
-    let mut png = Png::from_file(path)?;
+/// Same letters as `chunk_type` as a private, ancillary and safe to copy code
+fn suggested_code(chunk_type: &ChunkType) -> String {
+    let [a, b, c, d] = chunk_type.bytes();
+
+    String::from_utf8_lossy(&[
+        a.to_ascii_lowercase(),
+        b.to_ascii_lowercase(),
+        c.to_ascii_uppercase(),
+        d.to_ascii_lowercase(),
+    ])
+    .into_owned()
+}
+
+/// Which of the messages sharing a code a command works on
+#[derive(Clone, Copy)]
+pub enum Occurrence {
+    First,
+    Nth(usize),
+    All,
+}
+
+impl Occurrence {
+    pub const fn new(all: bool, index: Option<usize>) -> Self {
+        match (all, index) {
+            (true, _) => Self::All,
+            (false, Some(index)) => Self::Nth(index),
+            (false, None) => Self::First,
+        }
+    }
 
-    let chunk = png.remove_chunk(code)?;
+    /// Whether the message at `index` among the ones sharing a code is wanted
+    const fn includes(self, index: usize) -> bool {
+        match self {
+            Self::First => index == 0,
+            Self::Nth(n) => index == n,
+            Self::All => true,
+        }
+    }
+}
 
-    let out_bytes = png.as_bytes();
+/// Looks for the wanted messages with a code among the chunks it is offered
+struct MessageSearch {
+    chunk_type: ChunkType,
+    occurrence: Occurrence,
+    index: usize,
+    chunks: Vec<Chunk>,
+}
 
-    fs::write(path, out_bytes)?;
+impl MessageSearch {
+    const fn new(chunk_type: ChunkType, occurrence: Occurrence) -> Self {
+        Self {
+            chunk_type,
+            occurrence,
+            index: 0,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Keeps the chunk if it is a wanted message, returning whether the search is over
+    fn offer(&mut self, chunk: Chunk) -> bool {
+        if *chunk.chunk_type() != self.chunk_type {
+            return false;
+        }
+
+        let wanted = self.occurrence.includes(self.index);
+        self.index += 1;
+        if wanted {
+            self.chunks.push(chunk);
+        }
+
+        wanted && !matches!(self.occurrence, Occurrence::All)
+    }
+}
+
+pub fn decode(
+    path: &Path,
+    code: &str,
+    occurrence: Occurrence,
+    identity: Option<&Path>,
+    out: Option<&Path>,
+    limits: Limits,
+) -> Result<()> {
+    let identities = match identity {
+        Some(identity) => read_keys::<Identity>(identity)?,
+        None => Vec::new(),
+    };
+    let mut reader = PngReader::with_limits(open_input(path)?, limits)?;
+    let chunk_type = ChunkType::from_str(code)?;
 
-    let message = chunk.data_as_string()?;
+    let mut search = MessageSearch::new(chunk_type, occurrence);
 
-    println!("Removed message encoded with code {code}, it was {message}",);
+    // NOTE: errors are returned right away so they are not mistaken for a missing message
+    let mut done = false;
+    for chunk in reader.by_ref() {
+        if search.offer(chunk?) {
+            done = true;
+            break;
+        }
+    }
+
+    // NOTE: version 1.0 appended messages after IEND, so they are looked for there too
+    if !done {
+        let found = search.chunks.len();
+
+        let mut rest = Vec::new();
+        reader.into_inner().read_to_end(&mut rest)?;
+        for chunk in ChunkRef::parse_all(&rest, &limits).unwrap_or_default() {
+            if search.offer(chunk.into()) {
+                break;
+            }
+        }
+
+        if search.chunks.len() > found {
+            eprintln!(
+                "Warning: found messages after IEND, move them into the image with trailing --adopt"
+            );
+        }
+    }
+    let chunks = search.chunks;
+
+    if chunks.is_empty() {
+        bail!("Could not find message encoded with code {code}")
+    }
+
+    let mut passphrase = None;
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let payload = read_payload(chunk, &mut passphrase, &identities, &limits)?;
+
+        if let Some(out) = out {
+            write_output(out, payload.data())?;
+
+            let filename = payload
+                .filename()
+                .map(|filename| format!(", originally {filename}"))
+                .unwrap_or_default();
+            eprintln!(
+                "Saved the message with code {code} to {}, {} bytes of {}{filename}",
+                out.display(),
+                payload.data().len(),
+                payload.content_type()
+            );
+            continue;
+        }
+
+        let message = payload.as_text().map_or_else(
+            || format!("{}, save it with --out", payload.preview()),
+            str::to_string,
+        );
+
+        match occurrence {
+            Occurrence::All => println!("The encoded message {i} with code {code} is {message}"),
+            _ => println!("The encoded message with code {code} is {message}"),
+        }
+    }
 
     Ok(())
 }
 
-pub fn print(path: &Path) -> Result<()> {
-    let png = Png::from_file(path)?;
+pub fn remove(path: &Path, code: &str, occurrence: Occurrence, limits: Limits) -> Result<()> {
+    let mut png = read_png(path, limits)?;
+
+    let adopted = png.adopt_trailing_chunks();
+    if adopted > 0 {
+        eprintln!("Moved {adopted} chunks stored after IEND into the image");
+    }
+
+    let chunks = match occurrence {
+        Occurrence::First => vec![png.remove_chunk(code)?],
+        Occurrence::Nth(n) => vec![png.remove_nth_chunk(code, n)?],
+        Occurrence::All => png.remove_all_chunks(code)?,
+    };
+
+    if chunks.is_empty() {
+        bail!("Could not find message encoded with code {code}")
+    }
+
+    write_png(&png, path)?;
+
+    // NOTE: messages are only previewed, so one too big to inflate can not fail after the removal
+    for chunk in chunks {
+        let message = preview_message(chunk.data(), &limits);
+
+        eprintln!("Removed message encoded with code {code}, it was {message}",);
+    }
+
+    Ok(())
+}
+
+pub fn print(path: &Path, limits: Limits) -> Result<()> {
+    let bytes = read_input(path)?;
+    let png = PngRef::parse(&bytes, limits)?;
 
     println!("List of possible messages");
 
@@ -63,3 +453,740 @@ pub fn print(path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+pub struct TextOptions {
+    pub compress: bool,
+    pub international: bool,
+    pub language: Option<String>,
+    pub translated_keyword: Option<String>,
+}
+
+pub fn set_text(
+    path: PathBuf,
+    keyword: &str,
+    text: &str,
+    output: Option<PathBuf>,
+    options: &TextOptions,
+    limits: Limits,
+) -> Result<()> {
+    let mut png = read_png(&path, limits)?;
+
+    // NOTE: text that can not be stored as Latin-1 falls back to an iTXt chunk
+    let international = options.international
+        || options.language.is_some()
+        || options.translated_keyword.is_some()
+        || !text.chars().all(|c| u8::try_from(c).is_ok());
+
+    let text = if international {
+        TextChunk::international(
+            keyword,
+            text,
+            options.language.as_deref().unwrap_or_default(),
+            options.translated_keyword.as_deref().unwrap_or_default(),
+            options.compress,
+        )?
+    } else if options.compress {
+        TextChunk::compressed(keyword, text)?
+    } else {
+        TextChunk::new(keyword, text)?
+    };
+
+    png.set_text(&text)?;
+
+    let out_path = output.unwrap_or(path);
+
+    write_png(&png, &out_path)?;
+
+    Ok(())
+}
+
+pub fn get_text(path: &Path, keyword: Option<&str>, limits: Limits) -> Result<()> {
+    let png = read_png(path, limits)?;
+
+    if let Some(keyword) = keyword {
+        let Some(text) = png.text_by_keyword(keyword)? else {
+            bail!("Could not find text with keyword {keyword}")
+        };
+
+        println!("{}", text.text());
+
+        return Ok(());
+    }
+
+    for text in png.text_chunks() {
+        println!("{}", text?);
+    }
+
+    Ok(())
+}
+
+pub fn remove_text(path: &Path, keyword: &str, limits: Limits) -> Result<()> {
+    let mut png = read_png(path, limits)?;
+
+    let text = png.remove_text(keyword)?;
+
+    write_png(&png, path)?;
+
+    eprintln!(
+        "Removed text with keyword {keyword}, it was {}",
+        text.text()
+    );
+
+    Ok(())
+}
+
+pub fn frames(path: &Path, limits: Limits) -> Result<()> {
+    let png = read_png(path, limits)?;
+
+    let Some(animation) = png.animation_control()? else {
+        bail!("The image is not animated")
+    };
+
+    let plays = match animation.num_plays {
+        0 => "forever".to_string(),
+        plays => format!("{plays} times"),
+    };
+    println!(
+        "Animation with {} frames, played {plays}",
+        animation.num_frames
+    );
+
+    for (i, frame) in png.frames()?.iter().enumerate() {
+        let default = if frame.is_default_image() {
+            " (default image)"
+        } else {
+            ""
+        };
+
+        println!("Frame {i}{default}: {}", frame.control());
+    }
+
+    Ok(())
+}
+
+pub fn check(path: &Path, limits: Limits) -> Result<()> {
+    let bytes = read_input(path)?;
+    let png = PngRef::parse(&bytes, limits)?;
+
+    let violations = png.validate();
+
+    if violations.is_empty() {
+        println!("No violations found");
+        return Ok(());
+    }
+
+    for violation in &violations {
+        println!("{violation}");
+    }
+
+    bail!("Found {} violations", violations.len())
+}
+
+pub fn strip(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    options: &StripOptions,
+    limits: Limits,
+) -> Result<()> {
+    let mut png = read_png(&path, limits)?;
+    let trailing = png.trailing_data().len();
+
+    let dropped = png.strip(options);
+
+    write_png(&png, &output.unwrap_or(path))?;
+
+    for chunk in &dropped {
+        eprintln!(
+            "Dropped [{}] with {} bytes",
+            chunk.chunk_type(),
+            chunk.length()
+        );
+    }
+    if trailing > 0 {
+        eprintln!("Dropped {trailing} bytes of trailing data");
+    }
+    if dropped.is_empty() && trailing == 0 {
+        eprintln!("Nothing to strip");
+    }
+
+    Ok(())
+}
+
+pub fn exif(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    remove: &[String],
+    remove_gps: bool,
+    remove_thumbnail: bool,
+    limits: Limits,
+) -> Result<()> {
+    let mut png = read_png(&path, limits)?;
+
+    let Some(mut exif) = png.exif()? else {
+        bail!("The image has no eXIf chunk")
+    };
+
+    if remove.is_empty() && !remove_gps && !remove_thumbnail {
+        for entry in exif.entries() {
+            println!("{entry}");
+        }
+
+        return Ok(());
+    }
+
+    let mut removed = Vec::new();
+    for name in remove {
+        removed.push((name.as_str(), exif.remove_tag_by_name(name)?));
+    }
+    if remove_gps {
+        removed.push(("GPS", exif.remove_ifd(Ifd::Gps)?));
+    }
+    if remove_thumbnail {
+        removed.push(("thumbnail", exif.remove_ifd(Ifd::Thumbnail)?));
+    }
+
+    for (name, found) in removed {
+        if found {
+            eprintln!("Removed {name}");
+        } else {
+            eprintln!("No {name} tags found");
+        }
+    }
+
+    png.set_exif(&exif);
+    write_png(&png, &output.unwrap_or(path))?;
+
+    Ok(())
+}
+
+pub struct ColorOptions {
+    pub extract_icc: Option<PathBuf>,
+    pub icc: Option<PathBuf>,
+    pub icc_name: String,
+    pub srgb: Option<RenderingIntent>,
+    pub gamma: Option<u32>,
+}
+
+pub fn color(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    options: &ColorOptions,
+    limits: Limits,
+) -> Result<()> {
+    let modified = options.icc.is_some() || options.srgb.is_some() || options.gamma.is_some();
+    let to_stdout = is_stdio(output.as_deref().unwrap_or(&path));
+
+    if options.extract_icc.as_deref().is_some_and(is_stdio) && modified && to_stdout {
+        bail!("The ICC profile and the image can not both be written to stdout")
+    }
+    if options.icc.as_deref().is_some_and(is_stdio) && is_stdio(&path) {
+        bail!("The image and the ICC profile can not both be read from stdin")
+    }
+
+    let mut png = read_png(&path, limits)?;
+
+    if let Some(extract) = &options.extract_icc {
+        let Some(profile) = png.icc_profile()? else {
+            bail!("The image has no ICC profile")
+        };
+
+        write_output(extract, profile.profile())?;
+        eprintln!("Extracted {profile}");
+    }
+
+    if let Some(icc) = &options.icc {
+        png.set_icc_profile(&IccProfile::new(&options.icc_name, read_input(icc)?)?)?;
+    }
+    if let Some(rendering_intent) = options.srgb {
+        png.set_srgb(&Srgb { rendering_intent });
+    }
+    if let Some(gamma) = options.gamma {
+        png.set_gamma(&Gamma { gamma });
+    }
+
+    if modified {
+        write_png(&png, &output.unwrap_or(path))?;
+    } else if options.extract_icc.is_none() {
+        print_color_chunks(&png)?;
+    }
+
+    Ok(())
+}
+
+fn print_color_chunks(png: &Png) -> Result<()> {
+    let mut found = false;
+    let mut show = |code: &str, value: Option<String>| {
+        if let Some(value) = value {
+            println!("[{code}] {value}");
+            found = true;
+        }
+    };
+
+    show("gAMA", png.gamma()?.map(|gamma| gamma.to_string()));
+    show("cHRM", png.chromaticities()?.map(|chrm| chrm.to_string()));
+    show("sRGB", png.srgb()?.map(|srgb| srgb.to_string()));
+    show("iCCP", png.icc_profile()?.map(|icc| icc.to_string()));
+    show("cICP", png.cicp()?.map(|cicp| cicp.to_string()));
+
+    if !found {
+        println!("No color management chunks found");
+    }
+
+    Ok(())
+}
+
+pub struct TrailingOptions {
+    pub extract: Option<PathBuf>,
+    pub strip: bool,
+    pub set: Option<PathBuf>,
+    pub adopt: bool,
+}
+
+pub fn trailing(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    options: &TrailingOptions,
+    limits: Limits,
+) -> Result<()> {
+    let extract = options.extract.as_deref();
+    let set = options.set.as_deref();
+    let modified = options.strip || set.is_some() || options.adopt;
+    let to_stdout = is_stdio(output.as_deref().unwrap_or(&path));
+
+    if extract.is_some_and(is_stdio) && modified && to_stdout {
+        bail!("The trailing data and the image can not both be written to stdout")
+    }
+    if set.is_some_and(is_stdio) && is_stdio(&path) {
+        bail!("The image and the trailing data can not both be read from stdin")
+    }
+
+    let mut png = read_png(&path, limits)?;
+
+    if let Some(extract) = extract {
+        write_output(extract, png.trailing_data())?;
+        eprintln!(
+            "Extracted {} bytes of trailing data",
+            png.trailing_data().len()
+        );
+    }
+
+    if options.adopt {
+        let adopted = png.adopt_trailing_chunks();
+        if adopted == 0 {
+            bail!("The trailing data is not made of chunks")
+        }
+
+        write_png(&png, &output.unwrap_or(path))?;
+
+        eprintln!("Moved {adopted} chunks stored after IEND into the image");
+    } else if modified {
+        let data = match set {
+            Some(set) => read_input(set)?,
+            None => Vec::new(),
+        };
+
+        let previous = png.take_trailing_data();
+        png.set_trailing_data(data);
+
+        write_png(&png, &output.unwrap_or(path))?;
+
+        eprintln!(
+            "Replaced {} bytes of trailing data with {} bytes",
+            previous.len(),
+            png.trailing_data().len()
+        );
+    } else if extract.is_none() {
+        if png.trailing_data().is_empty() {
+            println!("No trailing data found");
+        } else {
+            let chunks = png.trailing_chunks();
+            if !chunks.is_empty() {
+                println!(
+                    "{} chunks stored after IEND, move them into the image with --adopt",
+                    chunks.len()
+                );
+            }
+
+            let payload = match String::from_utf8(png.trailing_data().to_vec()) {
+                Ok(text) => Payload::text(text),
+                Err(error) => Payload::binary(error.into_bytes()),
+            };
+
+            println!("{} bytes of trailing data", png.trailing_data().len());
+            println!("{}", payload.preview());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn salvage(
+    path: &Path,
+    output: Option<&Path>,
+    code: Option<&str>,
+    limits: Limits,
+) -> Result<()> {
+    if code.is_some() && output.is_some_and(is_stdio) {
+        bail!("The recovered message and image can not both be written to stdout")
+    }
+
+    let bytes = read_input(path)?;
+    let salvage = Png::salvage_with_limits(&bytes, limits)?;
+
+    for diagnostic in salvage.diagnostics() {
+        eprintln!("{diagnostic}");
+    }
+
+    for chunk in salvage.chunks() {
+        let status = match chunk.status() {
+            ChunkStatus::Valid => "",
+            ChunkStatus::CrcMismatch => " (crc mismatch)",
+            ChunkStatus::Truncated => " (truncated)",
+        };
+        eprintln!(
+            "[{}] at {}{status}",
+            chunk.chunk().chunk_type(),
+            chunk.offset()
+        );
+    }
+
+    if let Some(code) = code {
+        let chunk_type = ChunkType::from_str(code)?;
+
+        let mut recovered = None;
+        for chunk in salvage
+            .chunks()
+            .iter()
+            .filter(|chunk| chunk.chunk().chunk_type() == &chunk_type)
+        {
+            if matches!(chunk.status(), ChunkStatus::Valid) {
+                recovered = Some(chunk.chunk());
+                break;
+            }
+            eprintln!("Skipped the damaged message at {}", chunk.offset());
+        }
+        let Some(chunk) = recovered else {
+            bail!("No message with code {code} could be recovered")
+        };
+
+        let payload = read_payload(chunk, &mut None, &[], &limits)?;
+        let message = payload
+            .as_text()
+            .map_or_else(|| payload.preview(), str::to_string);
+        println!("The recovered message with code {code} is {message}");
+    }
+
+    if let Some(output) = output {
+        write_png(&salvage.into_png(), output)?;
+    }
+
+    Ok(())
+}
+
+pub fn keygen(secret_path: &Path, public_path: Option<&Path>, signing: bool) -> Result<()> {
+    let (secret, public) = if signing {
+        let key = SigningKey::generate();
+        (key.to_string(), key.verifying_key().to_string())
+    } else {
+        let identity = Identity::generate();
+        (identity.to_string(), identity.recipient().to_string())
+    };
+
+    let mut file = fs::OpenOptions::new();
+    file.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+
+    write!(
+        file.open(secret_path)?,
+        "# public key: {public}\n{secret}\n"
+    )?;
+
+    if let Some(public_path) = public_path {
+        fs::write(public_path, format!("{public}\n"))?;
+    }
+
+    println!("Public key: {public}");
+
+    Ok(())
+}
+
+pub fn verify_sig(path: &Path, trusted: Option<&Path>, limits: Limits) -> Result<()> {
+    let png = read_png(path, limits)?;
+
+    let trusted = match trusted {
+        Some(trusted) => Some(read_keys::<VerifyingKey>(trusted)?),
+        None => None,
+    };
+
+    let verifications = png.verify_signatures();
+    if verifications.is_empty() {
+        bail!("The image has no signed messages")
+    }
+
+    let mut failed = 0;
+    for verification in &verifications {
+        let verification = match verification {
+            Ok(verification) => verification,
+            Err(error) => {
+                failed += 1;
+                println!("Invalid signature, {error}");
+                continue;
+            }
+        };
+        let signature = &verification.signature;
+        let code = signature.code();
+        let fingerprint = signature.key().fingerprint();
+
+        let Some(message) = verification.message else {
+            failed += 1;
+            println!("Invalid signature by {fingerprint}, no message with code {code} matches it");
+            continue;
+        };
+
+        let trust = match &trusted {
+            Some(trusted) if !trusted.contains(signature.key()) => {
+                failed += 1;
+                ", untrusted signer"
+            }
+            Some(_) => ", trusted signer",
+            None => "",
+        };
+        let pixels = match verification.image_unchanged {
+            Some(true) => "pixels unchanged since signing",
+            Some(false) => "pixels altered since signing",
+            None => "pixels not covered by the signature",
+        };
+
+        println!("Message {message} with code {code} signed by {fingerprint}{trust}, {pixels}");
+    }
+
+    if failed > 0 {
+        bail!(
+            "{failed} of {} signatures could not be verified",
+            verifications.len()
+        )
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pngrs::{ChunkPlacement, ColorType, Compression, EncodeOptions, PixelBuffer};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("pngrs-{}-{name}", std::process::id()))
+    }
+
+    fn testing_png() -> Png {
+        let pixels =
+            PixelBuffer::new(2, 2, ColorType::Grayscale, 8, vec![0, 64, 128, 255]).unwrap();
+        Png::from_pixels(&pixels, &EncodeOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_is_stdio() {
+        assert!(is_stdio(Path::new("-")));
+        assert!(!is_stdio(Path::new("./-")));
+        assert!(!is_stdio(Path::new("image.png")));
+    }
+
+    #[test]
+    fn test_empty_passphrase_variable() {
+        // NOTE: no other test reads the passphrase, so changing the environment is safe
+        env::set_var(PASSPHRASE_VARIABLE, "");
+        let error = read_passphrase(true).unwrap_err();
+        env::remove_var(PASSPHRASE_VARIABLE);
+
+        assert!(error.to_string().contains("can not be empty"));
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let path = temp_path("round-trip.png");
+        let mut png = testing_png();
+        png.set_trailing_data(b"tail".to_vec());
+
+        write_png(&png, &path).unwrap();
+        let read = read_png(&path, Limits::default()).unwrap();
+        assert_eq!(read.chunks(), png.chunks());
+        assert_eq!(read.trailing_data(), b"tail");
+
+        write_output(&path, b"data").unwrap();
+        assert_eq!(read_input(&path).unwrap(), b"data");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_stdio_conflicts() {
+        // NOTE: conflicts are refused before anything is read from stdin
+        let stdio = || PathBuf::from(STDIO);
+        let file = temp_path("never-written.png");
+        let limits = Limits::default();
+
+        assert!(read_stdin_once(&stdio()).is_err());
+        assert!(salvage(&file, Some(&stdio()), Some("ruSt"), limits).is_err());
+
+        let mut options = TrailingOptions {
+            extract: Some(stdio()),
+            strip: true,
+            set: None,
+            adopt: false,
+        };
+        let error = trailing(stdio(), None, &options, limits).unwrap_err();
+        assert!(error.to_string().contains("written to stdout"));
+        options = TrailingOptions {
+            extract: None,
+            strip: false,
+            set: Some(stdio()),
+            adopt: false,
+        };
+        let error = trailing(stdio(), Some(file.clone()), &options, limits).unwrap_err();
+        assert!(error.to_string().contains("read from stdin"));
+
+        let mut options = ColorOptions {
+            extract_icc: Some(stdio()),
+            icc: None,
+            icc_name: String::new(),
+            srgb: None,
+            gamma: Some(45455),
+        };
+        let error = color(stdio(), None, &options, limits).unwrap_err();
+        assert!(error.to_string().contains("written to stdout"));
+
+        options.extract_icc = None;
+        options.icc = Some(stdio());
+        let error = color(stdio(), Some(file.clone()), &options, limits).unwrap_err();
+        assert!(error.to_string().contains("read from stdin"));
+
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_salvage_message() {
+        let path = temp_path("salvage.png");
+        let mut png = testing_png();
+        let mut payload = Payload::text("recovered".repeat(10));
+        payload.set_compression(Compression::Deflate);
+        png.insert_chunk(
+            Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                payload.to_bytes().unwrap(),
+            ),
+            ChunkPlacement::BeforeIend,
+        );
+        let mut bytes = png.as_bytes();
+        write_output(&path, &bytes).unwrap();
+        let limits = Limits::default();
+
+        salvage(&path, None, Some("ruSt"), limits).unwrap();
+
+        let crc = bytes.len() - 12 - 4;
+        bytes[crc] ^= 0xff;
+        write_output(&path, &bytes).unwrap();
+        let error = salvage(&path, None, Some("ruSt"), limits).unwrap_err();
+        assert!(error.to_string().contains("could be recovered"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_remove_message_above_limits() {
+        let path = temp_path("remove.png");
+        let mut png = testing_png();
+        let mut payload = Payload::text("big".repeat(10_000));
+        payload.set_compression(Compression::Deflate);
+        png.insert_chunk(
+            Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                payload.to_bytes().unwrap(),
+            ),
+            ChunkPlacement::BeforeIend,
+        );
+        write_png(&png, &path).unwrap();
+        let limits = Limits {
+            max_decompressed_size: 1000,
+            ..Limits::default()
+        };
+
+        remove(&path, "ruSt", Occurrence::First, limits).unwrap();
+        assert!(read_png(&path, limits)
+            .unwrap()
+            .chunk_by_type("ruSt")
+            .is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_message_after_iend() {
+        // NOTE: version 1.0 appended the raw message after IEND
+        let path = temp_path("legacy.png");
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend(
+            Chunk::new(
+                ChunkType::from_str("ruSt").unwrap(),
+                b"hello legacy".to_vec(),
+            )
+            .as_bytes(),
+        );
+        write_output(&path, &bytes).unwrap();
+        let limits = Limits::default();
+
+        decode(&path, "ruSt", Occurrence::First, None, None, limits).unwrap();
+        decode(&path, "ruSt", Occurrence::All, None, None, limits).unwrap();
+        assert!(decode(&path, "ruSt", Occurrence::Nth(1), None, None, limits).is_err());
+
+        let options = TrailingOptions {
+            extract: None,
+            strip: false,
+            set: None,
+            adopt: true,
+        };
+        trailing(path.clone(), None, &options, limits).unwrap();
+        let png = read_png(&path, limits).unwrap();
+        assert!(png.trailing_data().is_empty());
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"hello legacy");
+
+        write_output(&path, &bytes).unwrap();
+        remove(&path, "ruSt", Occurrence::First, limits).unwrap();
+        let png = read_png(&path, limits).unwrap();
+        assert!(png.trailing_data().is_empty());
+        assert!(png.chunk_by_type("ruSt").is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_decode_read_error_before_message() {
+        let path = temp_path("corrupt.png");
+        let mut png = testing_png();
+        png.insert_chunk(
+            Chunk::new(ChunkType::from_str("coRr").unwrap(), b"data".to_vec()),
+            ChunkPlacement::AfterIhdr,
+        );
+        for message in ["first", "second"] {
+            let data = Payload::text(message.to_string()).to_bytes().unwrap();
+            png.insert_chunk(
+                Chunk::new(ChunkType::from_str("ruSt").unwrap(), data),
+                ChunkPlacement::BeforeIend,
+            );
+        }
+
+        let mut bytes = png.as_bytes();
+        let crc = bytes.windows(4).position(|code| code == b"coRr").unwrap() + 8;
+        bytes[crc] ^= 0xff;
+        write_output(&path, &bytes).unwrap();
+
+        for occurrence in [Occurrence::First, Occurrence::Nth(1), Occurrence::All] {
+            let error =
+                decode(&path, "ruSt", occurrence, None, None, Limits::default()).unwrap_err();
+            assert!(!error.to_string().contains("Could not find"));
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
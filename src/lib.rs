@@ -1,5 +1,29 @@
 mod png;
 
+pub use png::apng::{AnimationControl, ApngError, BlendOp, DisposeOp, Frame, FrameControl};
+pub use png::chunk::chunk_ref::ChunkRef;
 pub use png::chunk::chunk_type::ChunkType;
 pub use png::chunk::Chunk;
-pub use png::Png;
+pub use png::color::{Chromaticities, Cicp, ColorError, Gamma, IccProfile, RenderingIntent, Srgb};
+pub use png::encryption::{decrypt, encrypt, is_encrypted, Cipher, EncryptionError, KdfParams};
+pub use png::exif::{tag_by_name, tag_name, ByteOrder, Exif, ExifEntry, ExifError, Ifd};
+pub use png::filter::{FilterError, FilterStrategy, FilterType};
+pub use png::ihdr::{ColorType, Ihdr, IhdrError, InterlaceMethod};
+pub use png::limits::{LimitError, Limits};
+pub use png::payload::{preview_message, Compression, Payload, PayloadError};
+pub use png::pixels::{EncodeOptions, PixelBuffer, PixelError};
+pub use png::png_ref::PngRef;
+pub use png::reader::PngReader;
+pub use png::recipients::{
+    decrypt_with_identities, encrypt_to_recipients, is_encrypted_to_recipients, Identity,
+    Recipient, RecipientError,
+};
+pub use png::salvage::{ChunkStatus, Diagnostic, Salvage, SalvagedChunk};
+pub use png::signature::{
+    MessageSignature, SignatureError, SigningKey, Verification, VerifyingKey,
+};
+pub use png::strip::{StripOptions, RENDERING_CHUNKS};
+pub use png::text::{TextChunk, TextError, TextKind};
+pub use png::validate::{Violation, ViolationKind};
+pub use png::writer::PngWriter;
+pub use png::{ChunkPlacement, Png};
@@ -1,6 +1,9 @@
 use args::{Args, Commands};
 use clap::Parser;
-use commands::{decode, encode, print, remove};
+use commands::{
+    check, color, decode, encode, exif, frames, get_text, keygen, print, remove, remove_text,
+    salvage, set_text, strip, trailing, verify_sig, Occurrence, TextOptions,
+};
 
 mod args;
 mod commands;
@@ -9,6 +12,7 @@ use anyhow::Result;
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let limits = args.limits.into();
 
     match args.command {
         Commands::Encode {
@@ -16,9 +20,86 @@ fn main() -> Result<()> {
             code,
             message,
             output,
-        } => encode(path, &code, message, output),
-        Commands::Decode { path, code } => decode(&path, &code),
-        Commands::Remove { path, code } => remove(&path, &code),
-        Commands::Print { path } => print(&path),
+            options,
+        } => encode(path, &code, message, output, &options, limits),
+        Commands::Decode {
+            path,
+            code,
+            all,
+            index,
+            identity,
+            out,
+        } => decode(
+            &path,
+            &code,
+            Occurrence::new(all, index),
+            identity.as_deref(),
+            out.as_deref(),
+            limits,
+        ),
+        Commands::Remove {
+            path,
+            code,
+            all,
+            index,
+        } => remove(&path, &code, Occurrence::new(all, index), limits),
+        Commands::Print { path } => print(&path, limits),
+        Commands::SetText {
+            path,
+            keyword,
+            text,
+            output,
+            compress,
+            international,
+            language,
+            translated_keyword,
+        } => set_text(
+            path,
+            &keyword,
+            &text,
+            output,
+            &TextOptions {
+                compress,
+                international,
+                language,
+                translated_keyword,
+            },
+            limits,
+        ),
+        Commands::GetText { path, keyword } => get_text(&path, keyword.as_deref(), limits),
+        Commands::RemoveText { path, keyword } => remove_text(&path, &keyword, limits),
+        Commands::Frames { path } => frames(&path, limits),
+        Commands::Check { path } => check(&path, limits),
+        Commands::Strip {
+            path,
+            output,
+            options,
+        } => strip(path, output, &options.into(), limits),
+        Commands::Exif {
+            path,
+            output,
+            remove,
+            remove_gps,
+            remove_thumbnail,
+        } => exif(path, output, &remove, remove_gps, remove_thumbnail, limits),
+        Commands::Color {
+            path,
+            output,
+            options,
+        } => color(path, output, &options.into(), limits),
+        Commands::Trailing {
+            path,
+            output,
+            options,
+        } => trailing(path, output, &options.into(), limits),
+        Commands::Salvage { path, output, code } => {
+            salvage(&path, output.as_deref(), code.as_deref(), limits)
+        }
+        Commands::Keygen {
+            secret,
+            public,
+            signing,
+        } => keygen(&secret, public.as_deref(), signing),
+        Commands::VerifySig { path, trusted } => verify_sig(&path, trusted.as_deref(), limits),
     }
 }
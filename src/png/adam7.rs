@@ -0,0 +1,189 @@
+use anyhow::{bail, Result};
+
+use super::filter::{self, FilterStrategy};
+use super::ihdr::Ihdr;
+use super::pixels::{image_size, PixelError};
+
+/// Starting column, starting row, column step and row step of the seven Adam7 passes
+const PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Width and height of the reduced image of each pass, either can be 0 for small images
+fn pass_sizes(width: u32, height: u32) -> impl Iterator<Item = (usize, u32, u32)> {
+    PASSES
+        .iter()
+        .enumerate()
+        .map(move |(pass, &(x0, y0, dx, dy))| {
+            (
+                pass,
+                (width + dx - x0 - 1) / dx,
+                (height + dy - y0 - 1) / dy,
+            )
+        })
+}
+
+/// Unfilters every pass of an interlaced image and scatters its pixels into a full image,
+/// refusing images whose pixels would take more than `limit` bytes
+pub fn deinterlace(data: &[u8], ihdr: &Ihdr, bpp: usize, limit: usize) -> Result<Vec<u8>> {
+    let bits_per_pixel = ihdr.bits_per_pixel();
+    let stride = ihdr.stride(ihdr.width());
+
+    let expected = pass_sizes(ihdr.width(), ihdr.height())
+        .filter(|&(_, width, height)| width > 0 && height > 0)
+        .try_fold(0_usize, |sum, (_, width, height)| {
+            (ihdr.stride(width) + 1)
+                .checked_mul(height as usize)?
+                .checked_add(sum)
+        })
+        .unwrap_or(usize::MAX);
+    if data.len() < expected {
+        bail!(PixelError::InvalidDataLength(expected, data.len()));
+    }
+
+    let mut pixels = vec![0; image_size(ihdr, limit)?];
+
+    let mut offset = 0;
+    for (pass, width, height) in pass_sizes(ihdr.width(), ihdr.height()) {
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        let pass_stride = ihdr.stride(width);
+        let length = (pass_stride + 1) * height as usize;
+        let reduced = filter::unfilter(&data[offset..offset + length], pass_stride, bpp)?;
+        offset += length;
+
+        let (x0, y0, dx, dy) = PASSES[pass];
+        for (py, row) in reduced.chunks_exact(pass_stride).enumerate() {
+            let y = y0 as usize + py * dy as usize;
+            let image_row = &mut pixels[y * stride..(y + 1) * stride];
+
+            for px in 0..width as usize {
+                let x = x0 as usize + px * dx as usize;
+                copy_pixel(row, px, image_row, x, bits_per_pixel);
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Splits a full image into the seven passes and filters each one
+pub fn interlace(pixels: &[u8], ihdr: &Ihdr, bpp: usize, strategy: FilterStrategy) -> Vec<u8> {
+    let bits_per_pixel = ihdr.bits_per_pixel();
+    let stride = ihdr.stride(ihdr.width());
+    let mut data = Vec::new();
+
+    for (pass, width, height) in pass_sizes(ihdr.width(), ihdr.height()) {
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        let pass_stride = ihdr.stride(width);
+        let mut reduced = vec![0; pass_stride * height as usize];
+
+        let (x0, y0, dx, dy) = PASSES[pass];
+        for (py, row) in reduced.chunks_exact_mut(pass_stride).enumerate() {
+            let y = y0 as usize + py * dy as usize;
+            let image_row = &pixels[y * stride..(y + 1) * stride];
+
+            for px in 0..width as usize {
+                let x = x0 as usize + px * dx as usize;
+                copy_pixel(image_row, x, row, px, bits_per_pixel);
+            }
+        }
+
+        data.extend(filter::filter(&reduced, pass_stride, bpp, strategy));
+    }
+
+    data
+}
+
+/// Copies the pixel at column `from_x` of `from` into column `to_x` of `to`,
+/// handling the packed layout of depths smaller than a byte
+fn copy_pixel(from: &[u8], from_x: usize, to: &mut [u8], to_x: usize, bits_per_pixel: usize) {
+    if bits_per_pixel >= 8 {
+        let bytes = bits_per_pixel / 8;
+        to[to_x * bytes..(to_x + 1) * bytes]
+            .copy_from_slice(&from[from_x * bytes..(from_x + 1) * bytes]);
+        return;
+    }
+
+    let mask = (1u8 << bits_per_pixel) - 1;
+    let from_bit = from_x * bits_per_pixel;
+    let from_shift = 8 - bits_per_pixel - from_bit % 8;
+    let value = (from[from_bit / 8] >> from_shift) & mask;
+
+    let to_bit = to_x * bits_per_pixel;
+    let to_shift = 8 - bits_per_pixel - to_bit % 8;
+    let byte = &mut to[to_bit / 8];
+    *byte = (*byte & !(mask << to_shift)) | (value << to_shift);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::ihdr::{ColorType, InterlaceMethod};
+
+    #[test]
+    fn test_pass_sizes() {
+        let sizes: Vec<(u32, u32)> = pass_sizes(8, 8).map(|(_, w, h)| (w, h)).collect();
+        assert_eq!(
+            sizes,
+            [(1, 1), (1, 1), (2, 1), (2, 2), (4, 2), (4, 4), (8, 4)]
+        );
+
+        let sizes: Vec<(u32, u32)> = pass_sizes(1, 1).map(|(_, w, h)| (w, h)).collect();
+        assert_eq!(
+            sizes,
+            [(1, 1), (0, 1), (1, 0), (0, 1), (1, 0), (0, 1), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_interlace_round_trip() {
+        // NOTE: sub-byte widths fill whole bytes so rows have no padding bits
+        for (color_type, bit_depth, bpp, width) in [
+            (ColorType::Grayscale, 1, 1, 16),
+            (ColorType::Grayscale, 2, 1, 12),
+            (ColorType::Rgb, 8, 3, 11),
+            (ColorType::Rgba, 16, 8, 11),
+        ] {
+            let ihdr = Ihdr::new(width, 9, bit_depth, color_type, InterlaceMethod::Adam7).unwrap();
+            let pixels: Vec<u8> = (0..=255u8)
+                .cycle()
+                .step_by(11)
+                .take(ihdr.stride(width) * 9)
+                .collect();
+
+            let data = interlace(&pixels, &ihdr, bpp, FilterStrategy::Adaptive);
+
+            assert_eq!(deinterlace(&data, &ihdr, bpp, usize::MAX).unwrap(), pixels);
+        }
+    }
+
+    #[test]
+    fn test_copy_packed_pixel() {
+        let mut to = [0b1111_1111];
+        copy_pixel(&[0b0100_0000], 0, &mut to, 3, 2);
+        assert_eq!(to, [0b1111_1101]);
+    }
+
+    #[test]
+    fn test_deinterlace_short_data() {
+        let ihdr = Ihdr::new(65535, 65535, 16, ColorType::Rgba, InterlaceMethod::Adam7).unwrap();
+
+        let error = deinterlace(&[0; 64], &ihdr, 8, usize::MAX).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PixelError::InvalidDataLength(_, 64))
+        ));
+    }
+}
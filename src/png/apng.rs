@@ -0,0 +1,587 @@
+use anyhow::{bail, Error, Result};
+use std::fmt::Display;
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, fixed_length, Chunk, ChunkError};
+use super::ihdr::Ihdr;
+use super::Png;
+
+/// Typed contents of the `acTL` chunk that marks a PNG as animated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationControl {
+    pub num_frames: u32,
+    /// Times the animation is played, 0 means forever
+    pub num_plays: u32,
+}
+
+/// Typed contents of an `fcTL` chunk, the region and timing of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameControl {
+    pub sequence_number: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: u32,
+    pub y_offset: u32,
+    pub delay_num: u16,
+    pub delay_den: u16,
+    pub dispose_op: DisposeOp,
+    pub blend_op: BlendOp,
+}
+
+/// How the frame region is left before rendering the next frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisposeOp {
+    None = 0,
+    Background = 1,
+    Previous = 2,
+}
+
+/// How the frame is drawn over the current canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Source = 0,
+    Over = 1,
+}
+
+/// A frame of an animation with its still compressed image data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    control: FrameControl,
+    data: Vec<u8>,
+    is_default_image: bool,
+}
+
+#[derive(Debug, ThisError)]
+pub enum ApngError {
+    #[error("The image is not animated, it has no acTL chunk")]
+    NotAnimated,
+    #[error("Invalid animation sequence number, expected {0} but found {1}")]
+    InvalidSequenceNumber(u32, u32),
+    #[error("Found fdAT chunk before any fcTL chunk")]
+    FrameDataWithoutControl,
+    #[error("The acTL chunk declares {0} frames but {1} were found")]
+    FrameCountMismatch(u32, usize),
+    #[error("Frame {0} does not fit inside the image")]
+    InvalidFrameRegion(usize),
+    #[error("The first frame must cover the whole image from its top left corner")]
+    InvalidFirstFrame,
+    #[error("Invalid dispose operation {0}")]
+    InvalidDisposeOp(u8),
+    #[error("Invalid blend operation {0}")]
+    InvalidBlendOp(u8),
+    #[error("Frame index {0} is out of range, the animation has {1} frames")]
+    FrameOutOfRange(usize, usize),
+    #[error("An animation needs at least one frame")]
+    NoFrames,
+    #[error("Frame {0} does not have the bit depth and color type of the first frame")]
+    IncompatibleFrame(usize),
+}
+
+impl AnimationControl {
+    pub const DATA_LENGTH: usize = 8;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data = fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::ACTL, data)?;
+
+        Ok(Self {
+            num_frames: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            num_plays: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [self.num_frames.to_be_bytes(), self.num_plays.to_be_bytes()].concat();
+
+        Chunk::new(ChunkType::ACTL, data)
+    }
+}
+
+impl FrameControl {
+    pub const DATA_LENGTH: usize = 26;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data = fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::FCTL, data)?;
+        let u32_at =
+            |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+        Ok(Self {
+            sequence_number: u32_at(0),
+            width: u32_at(4),
+            height: u32_at(8),
+            x_offset: u32_at(12),
+            y_offset: u32_at(16),
+            delay_num: u16::from_be_bytes([data[20], data[21]]),
+            delay_den: u16::from_be_bytes([data[22], data[23]]),
+            dispose_op: DisposeOp::try_from(data[24])?,
+            blend_op: BlendOp::try_from(data[25])?,
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            &self.sequence_number.to_be_bytes()[..],
+            &self.width.to_be_bytes(),
+            &self.height.to_be_bytes(),
+            &self.x_offset.to_be_bytes(),
+            &self.y_offset.to_be_bytes(),
+            &self.delay_num.to_be_bytes(),
+            &self.delay_den.to_be_bytes(),
+            &[self.dispose_op as u8, self.blend_op as u8],
+        ]
+        .concat();
+
+        Chunk::new(ChunkType::FCTL, data)
+    }
+
+    /// Delay of the frame in seconds, a denominator of 0 means hundredths of a second
+    #[must_use]
+    pub fn delay(&self) -> f64 {
+        let den = if self.delay_den == 0 {
+            100
+        } else {
+            self.delay_den
+        };
+
+        f64::from(self.delay_num) / f64::from(den)
+    }
+}
+
+impl Frame {
+    #[must_use]
+    pub const fn control(&self) -> &FrameControl {
+        &self.control
+    }
+
+    /// Compressed image data of the frame, as it would be stored in `IDAT` chunks
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Whether the frame is also the static image shown by decoders without APNG support
+    #[must_use]
+    pub const fn is_default_image(&self) -> bool {
+        self.is_default_image
+    }
+}
+
+impl TryFrom<u8> for DisposeOp {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::None,
+            1 => Self::Background,
+            2 => Self::Previous,
+            _ => bail!(ApngError::InvalidDisposeOp(value)),
+        })
+    }
+}
+
+impl TryFrom<u8> for BlendOp {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::Source,
+            1 => Self::Over,
+            _ => bail!(ApngError::InvalidBlendOp(value)),
+        })
+    }
+}
+
+impl Display for FrameControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {}), delay {} s, dispose {:?}, blend {:?}",
+            self.width,
+            self.height,
+            self.x_offset,
+            self.y_offset,
+            self.delay(),
+            self.dispose_op,
+            self.blend_op
+        )
+    }
+}
+
+impl Png {
+    pub fn animation_control(&self) -> Result<Option<AnimationControl>> {
+        self.chunks
+            .iter()
+            .find(|chunk| *chunk.chunk_type() == ChunkType::ACTL)
+            .map(|chunk| AnimationControl::from_data(chunk.data()))
+            .transpose()
+    }
+
+    /// Lists the frames of an animated image, checking the sequence numbers and frame count
+    pub fn frames(&self) -> Result<Vec<Frame>> {
+        let Some(animation) = self.animation_control()? else {
+            bail!(ApngError::NotAnimated);
+        };
+        let ihdr = self.ihdr()?;
+
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut sequence_number = 0;
+        let mut seen_idat = false;
+
+        for chunk in &self.chunks {
+            let chunk_type = chunk.chunk_type();
+
+            if *chunk_type == ChunkType::FCTL {
+                let control = FrameControl::from_data(chunk.data())?;
+                check_sequence(&mut sequence_number, control.sequence_number)?;
+
+                if control.width == 0
+                    || control.height == 0
+                    || u64::from(control.x_offset) + u64::from(control.width)
+                        > u64::from(ihdr.width())
+                    || u64::from(control.y_offset) + u64::from(control.height)
+                        > u64::from(ihdr.height())
+                {
+                    bail!(ApngError::InvalidFrameRegion(frames.len()));
+                }
+                if frames.is_empty()
+                    && (control.x_offset != 0
+                        || control.y_offset != 0
+                        || control.width != ihdr.width()
+                        || control.height != ihdr.height())
+                {
+                    bail!(ApngError::InvalidFirstFrame);
+                }
+
+                frames.push(Frame {
+                    control,
+                    data: Vec::new(),
+                    is_default_image: !seen_idat,
+                });
+            } else if *chunk_type == ChunkType::FDAT {
+                let Some(frame) = frames.last_mut() else {
+                    bail!(ApngError::FrameDataWithoutControl);
+                };
+                let Some((number, data)) = chunk.data().split_first_chunk::<4>() else {
+                    bail!(ChunkError::InvalidDataLength(
+                        chunk_type.clone(),
+                        4,
+                        chunk.data().len()
+                    ));
+                };
+                check_sequence(&mut sequence_number, u32::from_be_bytes(*number))?;
+
+                frame.data.extend_from_slice(data);
+            } else if *chunk_type == ChunkType::IDAT {
+                seen_idat = true;
+
+                if let Some(frame) = frames.last_mut().filter(|frame| frame.is_default_image) {
+                    frame.data.extend_from_slice(chunk.data());
+                }
+            }
+        }
+
+        if frames.len() != animation.num_frames as usize {
+            bail!(ApngError::FrameCountMismatch(
+                animation.num_frames,
+                frames.len()
+            ));
+        }
+
+        Ok(frames)
+    }
+
+    /// Builds a standalone PNG with the frame at `index`, keeping the palette and
+    /// the other chunks that describe how the image data is interpreted
+    pub fn extract_frame(&self, index: usize) -> Result<Self> {
+        let mut frames = self.frames()?;
+        if index >= frames.len() {
+            bail!(ApngError::FrameOutOfRange(index, frames.len()));
+        }
+        let frame = frames.swap_remove(index);
+
+        let ihdr = self.ihdr()?;
+        let ihdr = Ihdr::new(
+            frame.control.width,
+            frame.control.height,
+            ihdr.bit_depth(),
+            ihdr.color_type(),
+            ihdr.interlace_method(),
+        )?;
+
+        let mut chunks = vec![ihdr.to_chunk()];
+        chunks.extend(self.header_chunks().map(Chunk::clone));
+        chunks.extend(
+            frame
+                .data
+                .chunks(Self::IDAT_CHUNK_SIZE)
+                .map(|data| Chunk::new(ChunkType::IDAT, data.to_vec())),
+        );
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+
+        Ok(Self::from_chunks(chunks))
+    }
+
+    /// Reassembles an animation from still images and their frame controls.
+    ///
+    /// The first image is the default image and gives the size of the canvas, so its offsets are
+    /// ignored. The sizes of each control are taken from its image and sequence numbers are
+    /// assigned in order.
+    pub fn from_frames(frames: &[(Self, FrameControl)], num_plays: u32) -> Result<Self> {
+        let Some((base, _)) = frames.first() else {
+            bail!(ApngError::NoFrames);
+        };
+        let base_ihdr = base.ihdr()?;
+
+        let animation = AnimationControl {
+            num_frames: frames.len().try_into()?,
+            num_plays,
+        };
+
+        let mut chunks = vec![base_ihdr.to_chunk(), animation.to_chunk()];
+        chunks.extend(base.header_chunks().map(Chunk::clone));
+
+        let mut sequence_number = 0;
+        for (index, (png, control)) in frames.iter().enumerate() {
+            let ihdr = png.ihdr()?;
+            if ihdr.bit_depth() != base_ihdr.bit_depth()
+                || ihdr.color_type() != base_ihdr.color_type()
+            {
+                bail!(ApngError::IncompatibleFrame(index));
+            }
+
+            let mut control = FrameControl {
+                sequence_number,
+                width: ihdr.width(),
+                height: ihdr.height(),
+                ..*control
+            };
+            if index == 0 {
+                control.x_offset = 0;
+                control.y_offset = 0;
+            }
+            chunks.push(control.to_chunk());
+            sequence_number += 1;
+
+            let data = png.image_data();
+            for data in data.chunks(Self::IDAT_CHUNK_SIZE) {
+                if index == 0 {
+                    chunks.push(Chunk::new(ChunkType::IDAT, data.to_vec()));
+                } else {
+                    let data = [&sequence_number.to_be_bytes()[..], data].concat();
+                    chunks.push(Chunk::new(ChunkType::FDAT, data));
+                    sequence_number += 1;
+                }
+            }
+        }
+
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+
+        let png = Self::from_chunks(chunks);
+        png.frames()?;
+
+        Ok(png)
+    }
+
+    /// Chunks between `IHDR` and the image data, without the animation ones
+    fn header_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks
+            .iter()
+            .take_while(|chunk| ![ChunkType::IDAT, ChunkType::FCTL].contains(chunk.chunk_type()))
+            .filter(|chunk| ![ChunkType::IHDR, ChunkType::ACTL].contains(chunk.chunk_type()))
+    }
+}
+
+fn check_sequence(expected: &mut u32, found: u32) -> Result<()> {
+    if found != *expected {
+        bail!(ApngError::InvalidSequenceNumber(*expected, found));
+    }
+
+    *expected += 1;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::ihdr::ColorType;
+
+    fn testing_control() -> FrameControl {
+        FrameControl {
+            sequence_number: 0,
+            width: 0,
+            height: 0,
+            x_offset: 0,
+            y_offset: 0,
+            delay_num: 1,
+            delay_den: 10,
+            dispose_op: DisposeOp::None,
+            blend_op: BlendOp::Source,
+        }
+    }
+
+    fn testing_frame(width: u32, height: u32, value: u8) -> Png {
+        let data = vec![value; (width * height * 3) as usize];
+        Png::encode_pixels(width, height, ColorType::Rgb, 8, &data).unwrap()
+    }
+
+    fn testing_animation() -> Png {
+        let offset = FrameControl {
+            x_offset: 2,
+            y_offset: 1,
+            dispose_op: DisposeOp::Background,
+            blend_op: BlendOp::Over,
+            ..testing_control()
+        };
+
+        Png::from_frames(
+            &[
+                (testing_frame(4, 4, 10), testing_control()),
+                (testing_frame(2, 3, 20), offset),
+                (testing_frame(4, 4, 30), testing_control()),
+            ],
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_frame_control_round_trip() {
+        let control = FrameControl {
+            sequence_number: 7,
+            width: 3,
+            height: 4,
+            x_offset: 1,
+            y_offset: 2,
+            delay_num: 5,
+            delay_den: 0,
+            dispose_op: DisposeOp::Previous,
+            blend_op: BlendOp::Over,
+        };
+
+        let chunk = control.to_chunk();
+
+        assert_eq!(chunk.data().len(), FrameControl::DATA_LENGTH);
+        assert_eq!(FrameControl::from_data(chunk.data()).unwrap(), control);
+        assert!((control.delay() - 0.05).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_frames() {
+        let png = testing_animation();
+        let bytes = png.as_bytes();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+
+        let animation = png.animation_control().unwrap().unwrap();
+        let frames = png.frames().unwrap();
+
+        assert_eq!(animation.num_frames, 3);
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].is_default_image());
+        assert!(!frames[1].is_default_image());
+        assert_eq!(frames[1].control().width, 2);
+        assert_eq!(frames[1].control().x_offset, 2);
+        assert_eq!(frames[1].control().dispose_op, DisposeOp::Background);
+        assert_eq!(frames[2].control().sequence_number, 3);
+    }
+
+    #[test]
+    fn test_extract_frame() {
+        let png = testing_animation();
+        let frame = png.extract_frame(1).unwrap();
+
+        assert_eq!(
+            frame.decode_pixels().unwrap(),
+            testing_frame(2, 3, 20).decode_pixels().unwrap()
+        );
+        assert!(frame.animation_control().unwrap().is_none());
+        assert!(png.extract_frame(3).is_err());
+    }
+
+    #[test]
+    fn test_invalid_sequence_number() {
+        let mut png = testing_animation();
+        let index = png
+            .chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == ChunkType::FDAT)
+            .unwrap();
+        let mut data = png.chunks[index].data().to_vec();
+        data[3] = 9;
+        png.chunks[index] = Chunk::new(ChunkType::FDAT, data);
+
+        let error = png.frames().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ApngError>(),
+            Some(ApngError::InvalidSequenceNumber(2, 9))
+        ));
+    }
+
+    #[test]
+    fn test_frame_count_mismatch() {
+        let mut png = testing_animation();
+        let index = png
+            .chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == ChunkType::ACTL)
+            .unwrap();
+        png.chunks[index] = AnimationControl {
+            num_frames: 2,
+            num_plays: 0,
+        }
+        .to_chunk();
+
+        assert!(png.frames().is_err());
+    }
+
+    #[test]
+    fn test_not_animated() {
+        assert!(testing_frame(1, 1, 0).frames().is_err());
+    }
+
+    #[test]
+    fn test_frame_outside_canvas() {
+        let error = Png::from_frames(
+            &[
+                (testing_frame(2, 2, 0), testing_control()),
+                (testing_frame(4, 4, 0), testing_control()),
+            ],
+            1,
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            error.downcast_ref::<ApngError>(),
+            Some(ApngError::InvalidFrameRegion(1))
+        ));
+    }
+
+    #[test]
+    fn test_first_frame_covers_image() {
+        let offset = FrameControl {
+            x_offset: 1,
+            ..testing_control()
+        };
+        let png = Png::from_frames(&[(testing_frame(2, 2, 0), offset)], 1).unwrap();
+        assert_eq!(png.frames().unwrap()[0].control().x_offset, 0);
+
+        let mut png = testing_animation();
+        let index = png
+            .chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == ChunkType::FCTL)
+            .unwrap();
+        png.chunks[index] = FrameControl {
+            width: 3,
+            ..*png.frames().unwrap()[0].control()
+        }
+        .to_chunk();
+
+        assert!(matches!(
+            png.frames().unwrap_err().downcast_ref::<ApngError>(),
+            Some(ApngError::InvalidFirstFrame)
+        ));
+    }
+}
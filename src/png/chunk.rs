@@ -1,11 +1,15 @@
+pub mod chunk_ref;
 pub mod chunk_type;
 
 use anyhow::{bail, Error, Result};
+use chunk_ref::ChunkRef;
 use chunk_type::ChunkType;
 use crc::{Crc, CRC_32_ISO_HDLC};
 use std::fmt::Display;
+use std::io::Write;
 use thiserror::Error as ThisError;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
     length: u32,
     chunk_type: ChunkType,
@@ -27,12 +31,28 @@ pub enum ChunkError {
     NonMatchingDataLength(usize, usize),
     #[error("Error creating Chunk, Could not find Crc")]
     NoCrcProvided,
+    #[error("Invalid {0} chunk, expected {1} bytes of data but found {2}")]
+    InvalidDataLength(ChunkType, usize, usize),
+}
+
+/// The data of a chunk whose contents have a fixed length
+pub(super) fn fixed_length<const N: usize>(chunk_type: &ChunkType, data: &[u8]) -> Result<[u8; N]> {
+    match data.try_into() {
+        Ok(data) => Ok(data),
+        Err(_) => bail!(ChunkError::InvalidDataLength(
+            chunk_type.clone(),
+            N,
+            data.len()
+        )),
+    }
 }
 
 impl Chunk {
     pub const LENGTH_LENGTH: usize = 4;
     pub const TYPE_LENGTH: usize = 4;
     pub const CRC_LENGTH: usize = 4;
+    /// Largest length allowed by the specification, 2^31 - 1
+    pub const MAX_LENGTH: u32 = (1 << 31) - 1;
     pub const METEDATA_LENGTH: usize = Self::CRC_LENGTH + Self::LENGTH_LENGTH + Self::TYPE_LENGTH;
 
     #[must_use]
@@ -51,17 +71,29 @@ impl Chunk {
         }
     }
 
-    fn calculate_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
+    /// Builds a chunk from its already split parts, checking the provided crc
+    pub(crate) fn from_parts(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Result<Self> {
+        let actual_crc = Self::calculate_crc(&chunk_type, &data);
+        if crc != actual_crc {
+            bail!(ChunkError::InvalidCrc(crc, actual_crc));
+        }
+
+        Ok(Self {
+            length: data.len().try_into()?,
+            chunk_type,
+            data,
+            crc,
+        })
+    }
+
+    pub(crate) fn calculate_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
         let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC);
 
-        let crc_data: Vec<u8> = chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect();
+        let mut digest = crc.digest();
+        digest.update(&chunk_type.bytes());
+        digest.update(data);
 
-        crc.checksum(&crc_data)
+        digest.finalize()
     }
 
     #[must_use]
@@ -96,6 +128,16 @@ impl Chunk {
             .collect()
     }
 
+    /// Writes the chunk to `writer` without building an intermediate buffer
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&self.chunk_type.bytes())?;
+        writer.write_all(&self.data)?;
+        writer.write_all(&self.crc.to_be_bytes())?;
+
+        Ok(())
+    }
+
     pub fn data_as_string(&self) -> Result<String> {
         Ok(std::str::from_utf8(&self.data)?.to_string())
     }
@@ -105,54 +147,7 @@ impl TryFrom<&[u8]> for Chunk {
     type Error = Error;
 
     fn try_from(value: &[u8]) -> Result<Self> {
-        // NOTE: instead of split_at BufReader.read_exact can be used
-        // Example:
-        // let mut reader = BufReader::new(bytes);
-        // let mut buffer: [u8; 4] = [0, 0, 0, 0];
-        // reader.read_exact(&mut buffer)?;
-        // let data_length = u32::from_be_bytes(buffer);
-
-        if value.len() < Self::LENGTH_LENGTH {
-            bail!(ChunkError::NoDataLengthProvided);
-        }
-        let (length, value) = value.split_at(Self::LENGTH_LENGTH);
-        let length = u32::from_be_bytes(length.try_into()?);
-
-        if value.len() < Self::TYPE_LENGTH {
-            bail!(ChunkError::NoChunkTypeProvided);
-        }
-        let (chunk_code, value) = value.split_at(Self::TYPE_LENGTH);
-        let chunk_code: [u8; Self::TYPE_LENGTH] = chunk_code.try_into()?;
-        let chunk_type = ChunkType::try_from(chunk_code)?;
-
-        if value.len() < length as usize {
-            bail!(ChunkError::NonMatchingDataLength(
-                length as usize,
-                value.len(),
-            ));
-        }
-
-        let (data, value) = value.split_at(length as usize);
-        let data = data.to_vec();
-
-        if value.len() < Self::CRC_LENGTH {
-            bail!(ChunkError::NoCrcProvided);
-        }
-
-        let (crc, _) = value.split_at(Self::CRC_LENGTH);
-        let crc = u32::from_be_bytes(crc.try_into()?);
-
-        let actual_crc = Self::calculate_crc(&chunk_type, &data);
-        if crc != actual_crc {
-            bail!(ChunkError::InvalidCrc(crc, actual_crc));
-        }
-
-        Ok(Self {
-            length,
-            chunk_type,
-            data,
-            crc,
-        })
+        ChunkRef::try_from(value).map(Self::from)
     }
 }
 
@@ -276,6 +271,14 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_chunk_write_to() {
+        let chunk = testing_chunk();
+        let mut bytes = Vec::new();
+        chunk.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, chunk.as_bytes());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -0,0 +1,211 @@
+use anyhow::{bail, Error, Result};
+use std::fmt::Display;
+
+use super::chunk_type::ChunkType;
+use super::{Chunk, ChunkError};
+use crate::png::limits::Limits;
+
+/// A chunk that borrows its data from the buffer it was parsed from.
+///
+/// Useful when only the chunk layout is needed, as no chunk data is copied.
+/// It can be turned into an owned [`Chunk`] with `Chunk::from` when mutation is needed.
+pub struct ChunkRef<'a> {
+    length: u32,
+    chunk_type: ChunkType,
+    data: &'a [u8],
+    crc: u32,
+}
+
+impl<'a> ChunkRef<'a> {
+    #[must_use]
+    pub const fn length(&self) -> u32 {
+        self.length
+    }
+
+    #[must_use]
+    pub const fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    #[must_use]
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    #[must_use]
+    pub const fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Length of the chunk including the length, type and crc fields
+    #[must_use]
+    pub const fn total_length(&self) -> usize {
+        self.length as usize + Chunk::METEDATA_LENGTH
+    }
+
+    pub fn data_as_string(&self) -> Result<String> {
+        Ok(std::str::from_utf8(self.data)?.to_string())
+    }
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Parses a chunk from the start of `value`, refusing lengths above the given limits
+    pub fn parse(value: &'a [u8], limits: &Limits) -> Result<Self> {
+        if value.len() < Chunk::LENGTH_LENGTH {
+            bail!(ChunkError::NoDataLengthProvided);
+        }
+        let (length, value) = value.split_at(Chunk::LENGTH_LENGTH);
+        let length = u32::from_be_bytes(length.try_into()?);
+        limits.check_chunk_length(length)?;
+
+        if value.len() < Chunk::TYPE_LENGTH {
+            bail!(ChunkError::NoChunkTypeProvided);
+        }
+        let (chunk_code, value) = value.split_at(Chunk::TYPE_LENGTH);
+        let chunk_code: [u8; Chunk::TYPE_LENGTH] = chunk_code.try_into()?;
+        let chunk_type = ChunkType::try_from(chunk_code)?;
+
+        if value.len() < length as usize {
+            bail!(ChunkError::NonMatchingDataLength(
+                length as usize,
+                value.len(),
+            ));
+        }
+
+        let (data, value) = value.split_at(length as usize);
+
+        if value.len() < Chunk::CRC_LENGTH {
+            bail!(ChunkError::NoCrcProvided);
+        }
+
+        let (crc, _) = value.split_at(Chunk::CRC_LENGTH);
+        let crc = u32::from_be_bytes(crc.try_into()?);
+
+        let actual_crc = Chunk::calculate_crc(&chunk_type, data);
+        if crc != actual_crc {
+            bail!(ChunkError::InvalidCrc(crc, actual_crc));
+        }
+
+        Ok(Self {
+            length,
+            chunk_type,
+            data,
+            crc,
+        })
+    }
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Parses `value` as a sequence of complete chunks with valid crcs and nothing else
+    pub fn parse_all(mut value: &'a [u8], limits: &Limits) -> Result<Vec<Self>> {
+        let mut chunks = Vec::new();
+
+        while !value.is_empty() {
+            limits.check_chunk_count(chunks.len() + 1)?;
+
+            let chunk = Self::parse(value, limits)?;
+            value = &value[chunk.total_length()..];
+            chunks.push(chunk);
+        }
+
+        Ok(chunks)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for ChunkRef<'a> {
+    type Error = Error;
+
+    fn try_from(value: &'a [u8]) -> Result<Self> {
+        Self::parse(value, &Limits::unlimited())
+    }
+}
+
+impl From<ChunkRef<'_>> for Chunk {
+    fn from(value: ChunkRef<'_>) -> Self {
+        Self {
+            length: value.length,
+            chunk_type: value.chunk_type,
+            data: value.data.to_vec(),
+            crc: value.crc,
+        }
+    }
+}
+
+impl Display for ChunkRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Chunk {{")?;
+        writeln!(f, "  Length: {}", self.length())?;
+        writeln!(f, "  Type: {}", self.chunk_type())?;
+        writeln!(f, "  Data: {} bytes", self.data().len())?;
+        writeln!(f, "  Crc: {}", self.crc())?;
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::limits::LimitError;
+    use std::str::FromStr;
+
+    fn testing_chunk_bytes() -> Vec<u8> {
+        Chunk::new(
+            ChunkType::from_str("RuSt").unwrap(),
+            b"This is where your secret message will be!".to_vec(),
+        )
+        .as_bytes()
+    }
+
+    #[test]
+    fn test_chunk_ref_borrows_data() {
+        let bytes = testing_chunk_bytes();
+        let chunk = ChunkRef::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2_882_656_334);
+        assert_eq!(chunk.total_length(), bytes.len());
+        assert!(std::ptr::eq(chunk.data().as_ptr(), bytes[8..].as_ptr()));
+    }
+
+    #[test]
+    fn test_chunk_ref_to_owned() {
+        let bytes = testing_chunk_bytes();
+        let chunk = Chunk::from(ChunkRef::try_from(bytes.as_ref()).unwrap());
+
+        assert_eq!(chunk.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_chunk_ref_invalid_crc() {
+        let mut bytes = testing_chunk_bytes();
+        *bytes.last_mut().unwrap() ^= 0xff;
+
+        assert!(ChunkRef::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_chunk_ref_length_above_specification() {
+        let mut bytes = testing_chunk_bytes();
+        bytes[..4].copy_from_slice(&(Chunk::MAX_LENGTH + 1).to_be_bytes());
+
+        let error = ChunkRef::parse(&bytes, &Limits::unlimited()).err().unwrap();
+
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::ChunkTooLong(_, Chunk::MAX_LENGTH))
+        ));
+
+        let error = ChunkRef::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::ChunkTooLong(_, Chunk::MAX_LENGTH))
+        ));
+
+        let error = ChunkRef::parse(&bytes, &Limits::default()).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::ChunkTooLong(_, 268_435_456))
+        ));
+    }
+}
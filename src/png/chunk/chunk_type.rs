@@ -2,7 +2,7 @@ use anyhow::{bail, Error, Result};
 use std::{fmt::Display, str::FromStr};
 use thiserror::Error as ThisError;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChunkType {
     code: [u8; 4],
 }
@@ -15,9 +15,31 @@ pub enum ChunkTypeError {
     InvalidByte(u8),
 }
 
-// NOTE: Functions are allowed unused for future extension
-#[allow(unused)]
 impl ChunkType {
+    pub const IHDR: Self = Self { code: *b"IHDR" };
+    pub const PLTE: Self = Self { code: *b"PLTE" };
+    pub const IDAT: Self = Self { code: *b"IDAT" };
+    pub const IEND: Self = Self { code: *b"IEND" };
+    pub const TEXT: Self = Self { code: *b"tEXt" };
+    pub const ZTXT: Self = Self { code: *b"zTXt" };
+    pub const ITXT: Self = Self { code: *b"iTXt" };
+    pub const ACTL: Self = Self { code: *b"acTL" };
+    pub const FCTL: Self = Self { code: *b"fcTL" };
+    pub const FDAT: Self = Self { code: *b"fdAT" };
+    pub const GAMA: Self = Self { code: *b"gAMA" };
+    pub const CHRM: Self = Self { code: *b"cHRM" };
+    pub const SRGB: Self = Self { code: *b"sRGB" };
+    pub const ICCP: Self = Self { code: *b"iCCP" };
+    pub const CICP: Self = Self { code: *b"cICP" };
+    pub const EXIF: Self = Self { code: *b"eXIf" };
+    pub const TRNS: Self = Self { code: *b"tRNS" };
+    pub const SBIT: Self = Self { code: *b"sBIT" };
+    pub const PHYS: Self = Self { code: *b"pHYs" };
+    pub const BKGD: Self = Self { code: *b"bKGD" };
+    pub const CLLI: Self = Self { code: *b"cLLI" };
+    pub const MDCV: Self = Self { code: *b"mDCV" };
+    pub const SGNT: Self = Self { code: *b"sgNt" };
+
     #[must_use]
     pub const fn bytes(&self) -> [u8; 4] {
         self.code
@@ -31,23 +53,28 @@ impl ChunkType {
         self.code[byte - 1] & (1 << 5) != 0
     }
 
-    const fn is_critical(&self) -> bool {
+    #[must_use]
+    pub const fn is_critical(&self) -> bool {
         !self.is_property_bit_on(1)
     }
 
-    const fn is_public(&self) -> bool {
+    #[must_use]
+    pub const fn is_public(&self) -> bool {
         !self.is_property_bit_on(2)
     }
 
-    const fn is_reserved_bit_valid(&self) -> bool {
+    #[must_use]
+    pub const fn is_reserved_bit_valid(&self) -> bool {
         !self.is_property_bit_on(3)
     }
 
-    const fn is_safe_to_copy(&self) -> bool {
+    #[must_use]
+    pub const fn is_safe_to_copy(&self) -> bool {
         self.is_property_bit_on(4)
     }
 
-    const fn is_valid(&self) -> bool {
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
         self.is_reserved_bit_valid()
     }
 }
@@ -0,0 +1,513 @@
+use anyhow::{bail, Error, Result};
+use flate2::Compression;
+use std::fmt::Display;
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, fixed_length, Chunk};
+use super::limits::Limits;
+use super::text::{latin1_decode, latin1_encode, validate_keyword};
+use super::{zlib, ChunkPlacement, Png};
+
+/// Typed contents of the `gAMA` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gamma {
+    /// Image gamma times 100000, 45455 for the usual 1/2.2
+    pub gamma: u32,
+}
+
+/// Typed contents of the `cHRM` chunk, CIE 1931 coordinates times 100000
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chromaticities {
+    pub white_x: u32,
+    pub white_y: u32,
+    pub red_x: u32,
+    pub red_y: u32,
+    pub green_x: u32,
+    pub green_y: u32,
+    pub blue_x: u32,
+    pub blue_y: u32,
+}
+
+/// Typed contents of the `sRGB` chunk, the image uses the sRGB color space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Srgb {
+    pub rendering_intent: RenderingIntent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderingIntent {
+    Perceptual = 0,
+    RelativeColorimetric = 1,
+    Saturation = 2,
+    AbsoluteColorimetric = 3,
+}
+
+/// Typed contents of the `iCCP` chunk, an embedded ICC profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IccProfile {
+    name: String,
+    profile: Vec<u8>,
+}
+
+/// Typed contents of the `cICP` chunk, coding-independent code points from ITU-T H.273
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cicp {
+    pub color_primaries: u8,
+    pub transfer_function: u8,
+    /// Always 0 in PNG, as images are only stored as RGB
+    pub matrix_coefficients: u8,
+    pub full_range: bool,
+}
+
+#[derive(Debug, ThisError)]
+pub enum ColorError {
+    #[error("Invalid rendering intent {0}")]
+    InvalidRenderingIntent(u8),
+    #[error("Invalid ICC profile name {0:?}, it must be 1 to 79 printable Latin-1 characters without leading, trailing or consecutive spaces")]
+    InvalidProfileName(String),
+    #[error("Error parsing iCCP chunk, missing null separator")]
+    MissingNullSeparator,
+    #[error("Error parsing iCCP chunk, unknown compression method {0}")]
+    InvalidCompressionMethod(u8),
+    #[error("Invalid cICP matrix coefficients {0}, PNG images only support 0")]
+    InvalidMatrixCoefficients(u8),
+    #[error("Invalid cICP full range flag {0}")]
+    InvalidRangeFlag(u8),
+}
+
+impl Gamma {
+    pub const DATA_LENGTH: usize = 4;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data = fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::GAMA, data)?;
+
+        Ok(Self {
+            gamma: u32::from_be_bytes(data),
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::GAMA, self.gamma.to_be_bytes().to_vec())
+    }
+}
+
+impl Chromaticities {
+    pub const DATA_LENGTH: usize = 32;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data = fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::CHRM, data)?;
+        let u32_at =
+            |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+        Ok(Self {
+            white_x: u32_at(0),
+            white_y: u32_at(4),
+            red_x: u32_at(8),
+            red_y: u32_at(12),
+            green_x: u32_at(16),
+            green_y: u32_at(20),
+            blue_x: u32_at(24),
+            blue_y: u32_at(28),
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        let data = [
+            self.white_x,
+            self.white_y,
+            self.red_x,
+            self.red_y,
+            self.green_x,
+            self.green_y,
+            self.blue_x,
+            self.blue_y,
+        ]
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+
+        Chunk::new(ChunkType::CHRM, data)
+    }
+}
+
+impl Srgb {
+    pub const DATA_LENGTH: usize = 1;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let [intent] = fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::SRGB, data)?;
+
+        Ok(Self {
+            rendering_intent: RenderingIntent::try_from(intent)?,
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::SRGB, vec![self.rendering_intent as u8])
+    }
+}
+
+impl IccProfile {
+    /// Creates a profile from the uncompressed ICC data
+    pub fn new(name: &str, profile: Vec<u8>) -> Result<Self> {
+        if validate_keyword(name).is_err() {
+            bail!(ColorError::InvalidProfileName(name.to_string()));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            profile,
+        })
+    }
+
+    /// Parses the chunk data, inflating the profile only up to the given limits
+    pub fn from_data(data: &[u8], limits: &Limits) -> Result<Self> {
+        let Some(index) = data.iter().position(|&b| b == 0) else {
+            bail!(ColorError::MissingNullSeparator);
+        };
+        let (name, rest) = (&data[..index], &data[index + 1..]);
+
+        let Some((&method, compressed)) = rest.split_first() else {
+            bail!(ColorError::MissingNullSeparator);
+        };
+        if method != 0 {
+            bail!(ColorError::InvalidCompressionMethod(method));
+        }
+
+        let profile = zlib::decompress(compressed, limits.max_decompressed_size)?;
+
+        Self::new(&latin1_decode(name), profile)
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The uncompressed ICC profile, ready to be saved as an `.icc` file
+    #[must_use]
+    pub fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = latin1_encode(&self.name)?;
+        data.extend([0, 0]);
+        data.extend(zlib::compress(&self.profile, Compression::default())?);
+
+        Ok(Chunk::new(ChunkType::ICCP, data))
+    }
+}
+
+impl Cicp {
+    pub const DATA_LENGTH: usize = 4;
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let [color_primaries, transfer_function, matrix_coefficients, full_range] =
+            fixed_length::<{ Self::DATA_LENGTH }>(&ChunkType::CICP, data)?;
+
+        if matrix_coefficients != 0 {
+            bail!(ColorError::InvalidMatrixCoefficients(matrix_coefficients));
+        }
+
+        let full_range = match full_range {
+            0 => false,
+            1 => true,
+            _ => bail!(ColorError::InvalidRangeFlag(full_range)),
+        };
+
+        Ok(Self {
+            color_primaries,
+            transfer_function,
+            matrix_coefficients,
+            full_range,
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        let data = vec![
+            self.color_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            u8::from(self.full_range),
+        ];
+
+        Chunk::new(ChunkType::CICP, data)
+    }
+}
+
+impl TryFrom<u8> for RenderingIntent {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::Perceptual,
+            1 => Self::RelativeColorimetric,
+            2 => Self::Saturation,
+            3 => Self::AbsoluteColorimetric,
+            _ => bail!(ColorError::InvalidRenderingIntent(value)),
+        })
+    }
+}
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gamma {}", Fixed(self.gamma))
+    }
+}
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "white ({}, {}), red ({}, {}), green ({}, {}), blue ({}, {})",
+            Fixed(self.white_x),
+            Fixed(self.white_y),
+            Fixed(self.red_x),
+            Fixed(self.red_y),
+            Fixed(self.green_x),
+            Fixed(self.green_y),
+            Fixed(self.blue_x),
+            Fixed(self.blue_y),
+        )
+    }
+}
+
+impl Display for Srgb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} rendering intent", self.rendering_intent)
+    }
+}
+
+impl Display for IccProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ICC profile {}, {} bytes", self.name, self.profile.len())
+    }
+}
+
+impl Display for Cicp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "primaries {}, transfer function {}, matrix coefficients {}, {} range",
+            self.color_primaries,
+            self.transfer_function,
+            self.matrix_coefficients,
+            if self.full_range { "full" } else { "narrow" }
+        )
+    }
+}
+
+/// Fixed point value of the color chunks, stored times 100000
+struct Fixed(u32);
+
+impl Display for Fixed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:05}", self.0 / 100_000, self.0 % 100_000)
+    }
+}
+
+impl Png {
+    pub fn gamma(&self) -> Result<Option<Gamma>> {
+        self.color_chunk(&ChunkType::GAMA)
+            .map(|chunk| Gamma::from_data(chunk.data()))
+            .transpose()
+    }
+
+    pub fn chromaticities(&self) -> Result<Option<Chromaticities>> {
+        self.color_chunk(&ChunkType::CHRM)
+            .map(|chunk| Chromaticities::from_data(chunk.data()))
+            .transpose()
+    }
+
+    pub fn srgb(&self) -> Result<Option<Srgb>> {
+        self.color_chunk(&ChunkType::SRGB)
+            .map(|chunk| Srgb::from_data(chunk.data()))
+            .transpose()
+    }
+
+    pub fn icc_profile(&self) -> Result<Option<IccProfile>> {
+        self.color_chunk(&ChunkType::ICCP)
+            .map(|chunk| IccProfile::from_data(chunk.data(), &self.limits))
+            .transpose()
+    }
+
+    pub fn cicp(&self) -> Result<Option<Cicp>> {
+        self.color_chunk(&ChunkType::CICP)
+            .map(|chunk| Cicp::from_data(chunk.data()))
+            .transpose()
+    }
+
+    pub fn set_gamma(&mut self, gamma: &Gamma) {
+        self.replace_color_chunk(gamma.to_chunk());
+    }
+
+    pub fn set_chromaticities(&mut self, chromaticities: &Chromaticities) {
+        self.replace_color_chunk(chromaticities.to_chunk());
+    }
+
+    /// Sets the `sRGB` chunk, removing any `iCCP` chunk as both can not be present
+    pub fn set_srgb(&mut self, srgb: &Srgb) {
+        self.chunks
+            .retain(|chunk| *chunk.chunk_type() != ChunkType::ICCP);
+        self.replace_color_chunk(srgb.to_chunk());
+    }
+
+    /// Sets the `iCCP` chunk, removing any `sRGB` chunk as both can not be present
+    pub fn set_icc_profile(&mut self, profile: &IccProfile) -> Result<()> {
+        let chunk = profile.to_chunk()?;
+
+        self.chunks
+            .retain(|chunk| *chunk.chunk_type() != ChunkType::SRGB);
+        self.replace_color_chunk(chunk);
+
+        Ok(())
+    }
+
+    pub fn set_cicp(&mut self, cicp: &Cicp) {
+        self.replace_color_chunk(cicp.to_chunk());
+    }
+
+    fn color_chunk(&self, chunk_type: &ChunkType) -> Option<&Chunk> {
+        self.chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type() == chunk_type)
+    }
+
+    /// Replaces the chunk of the same type in place, or adds it right after `IHDR`
+    /// as color chunks must come before `PLTE` and `IDAT`
+    fn replace_color_chunk(&mut self, chunk: Chunk) {
+        let index = self
+            .chunks
+            .iter()
+            .position(|existing| existing.chunk_type() == chunk.chunk_type());
+
+        match index {
+            Some(index) => {
+                let chunk_type = chunk.chunk_type().clone();
+                self.chunks[index] = chunk;
+
+                // NOTE: only the first chunk of the type is kept
+                let mut seen = false;
+                self.chunks.retain(|chunk| {
+                    let duplicate = seen && *chunk.chunk_type() == chunk_type;
+                    seen |= *chunk.chunk_type() == chunk_type;
+                    !duplicate
+                });
+            }
+            None => self.insert_chunk(chunk, ChunkPlacement::AfterIhdr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_image_color_chunks() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        assert_eq!(png.gamma().unwrap(), Some(Gamma { gamma: 45455 }));
+        assert_eq!(
+            png.srgb().unwrap(),
+            Some(Srgb {
+                rendering_intent: RenderingIntent::Perceptual
+            })
+        );
+        assert!(png.chromaticities().unwrap().is_none());
+        assert!(png.icc_profile().unwrap().is_none());
+        assert!(png.cicp().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chromaticities_round_trip() {
+        let chromaticities = Chromaticities {
+            white_x: 31270,
+            white_y: 32900,
+            red_x: 64000,
+            red_y: 33000,
+            green_x: 30000,
+            green_y: 60000,
+            blue_x: 15000,
+            blue_y: 6000,
+        };
+        let chunk = chromaticities.to_chunk();
+
+        assert_eq!(chunk.data().len(), Chromaticities::DATA_LENGTH);
+        assert_eq!(
+            Chromaticities::from_data(chunk.data()).unwrap(),
+            chromaticities
+        );
+        assert_eq!(
+            chromaticities.to_string(),
+            "white (0.31270, 0.32900), red (0.64000, 0.33000), green (0.30000, 0.60000), blue (0.15000, 0.06000)"
+        );
+    }
+
+    #[test]
+    fn test_icc_profile_round_trip() {
+        let profile = IccProfile::new("Display P3", b"fake icc profile".repeat(20)).unwrap();
+        let chunk = profile.to_chunk().unwrap();
+
+        assert!(chunk.data().starts_with(b"Display P3\0\0"));
+        assert_eq!(
+            IccProfile::from_data(chunk.data(), &Limits::default()).unwrap(),
+            profile
+        );
+
+        let limits = Limits {
+            max_decompressed_size: 100,
+            ..Limits::default()
+        };
+        assert!(IccProfile::from_data(chunk.data(), &limits).is_err());
+        assert!(IccProfile::new(" bad name", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_cicp() {
+        let cicp = Cicp {
+            color_primaries: 9,
+            transfer_function: 16,
+            matrix_coefficients: 0,
+            full_range: true,
+        };
+
+        assert_eq!(Cicp::from_data(&[9, 16, 0, 1]).unwrap(), cicp);
+        assert_eq!(cicp.to_chunk().data(), [9, 16, 0, 1]);
+        assert!(Cicp::from_data(&[9, 16, 1, 1]).is_err());
+        assert!(Cicp::from_data(&[9, 16, 0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        assert!(Gamma::from_data(&[0; 3]).is_err());
+        assert!(Srgb::from_data(&[]).is_err());
+        assert!(Srgb::from_data(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_png_replace_color_chunks() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        png.set_gamma(&Gamma { gamma: 100_000 });
+        assert_eq!(png.gamma().unwrap(), Some(Gamma { gamma: 100_000 }));
+        assert_eq!(png.chunks()[2].chunk_type(), &ChunkType::GAMA);
+
+        let profile = IccProfile::new("Profile", b"icc".to_vec()).unwrap();
+        png.set_icc_profile(&profile).unwrap();
+        assert_eq!(png.icc_profile().unwrap(), Some(profile));
+        assert!(png.srgb().unwrap().is_none());
+        assert_eq!(png.chunks()[1].chunk_type(), &ChunkType::ICCP);
+
+        png.set_srgb(&Srgb {
+            rendering_intent: RenderingIntent::Saturation,
+        });
+        assert!(png.icc_profile().unwrap().is_none());
+        assert_eq!(png.chunks().len(), 7);
+    }
+}
@@ -0,0 +1,361 @@
+use aes_gcm::Aes256Gcm;
+use anyhow::{bail, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use thiserror::Error as ThisError;
+
+/// Marks the start of an encrypted message, so decoders can tell it apart from plain text
+pub const MAGIC: [u8; 4] = *b"PNGe";
+pub const VERSION: u8 = 1;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+const TAG_LENGTH: usize = 16;
+const HEADER_LENGTH: usize = MAGIC.len() + 2 + 12 + SALT_LENGTH + NONCE_LENGTH;
+
+/// Authenticated cipher used to encrypt a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cipher {
+    #[default]
+    ChaCha20Poly1305,
+    Aes256Gcm,
+}
+
+impl Cipher {
+    const fn id(self) -> u8 {
+        match self {
+            Self::ChaCha20Poly1305 => 1,
+            Self::Aes256Gcm => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Cipher {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(Self::ChaCha20Poly1305),
+            2 => Ok(Self::Aes256Gcm),
+            _ => bail!(EncryptionError::UnknownCipher(value)),
+        }
+    }
+}
+
+impl std::fmt::Display for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ChaCha20Poly1305 => write!(f, "ChaCha20-Poly1305"),
+            Self::Aes256Gcm => write!(f, "AES-256-GCM"),
+        }
+    }
+}
+
+/// Cost of the Argon2id key derivation, stored in the message so it can be raised later
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Costs accepted when decrypting, so a crafted message can not exhaust memory or time
+    pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+    pub const MAX_ITERATIONS: u32 = 64;
+    pub const MAX_PARALLELISM: u32 = 16;
+
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; KEY_LENGTH]> {
+        let Ok(params) = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LENGTH),
+        ) else {
+            bail!(EncryptionError::InvalidKdfParams(*self))
+        };
+
+        let mut key = [0; KEY_LENGTH];
+        if Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, salt, &mut key)
+            .is_err()
+        {
+            bail!(EncryptionError::InvalidKdfParams(*self))
+        }
+
+        Ok(key)
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum EncryptionError {
+    #[error("Error decrypting message, it is not encrypted")]
+    NotEncrypted,
+    #[error("Error decrypting message, version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Error decrypting message, unknown cipher {0}")]
+    UnknownCipher(u8),
+    #[error("Invalid key derivation parameters {0:?}")]
+    InvalidKdfParams(KdfParams),
+    #[error("Error decrypting message, the message is truncated")]
+    Truncated,
+    #[error("Error decrypting message, wrong passphrase or the message was tampered with")]
+    DecryptionFailed,
+}
+
+/// Values needed to decrypt a message, stored in front of the ciphertext
+struct Header {
+    cipher: Cipher,
+    params: KdfParams,
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+}
+
+impl Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH);
+
+        bytes.extend(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.cipher.id());
+        bytes.extend(self.params.memory_kib.to_be_bytes());
+        bytes.extend(self.params.iterations.to_be_bytes());
+        bytes.extend(self.params.parallelism.to_be_bytes());
+        bytes.extend(self.salt);
+        bytes.extend(self.nonce);
+
+        bytes
+    }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        if !is_encrypted(data) {
+            bail!(EncryptionError::NotEncrypted)
+        }
+
+        let Some(header) = data.get(..HEADER_LENGTH) else {
+            bail!(EncryptionError::Truncated)
+        };
+        let (version, rest) = header[MAGIC.len()..].split_at(1);
+        let (cipher, rest) = rest.split_at(1);
+        let (params, rest) = rest.split_at(12);
+        let (salt, nonce) = rest.split_at(SALT_LENGTH);
+
+        if version[0] != VERSION {
+            bail!(EncryptionError::UnsupportedVersion(version[0]))
+        }
+
+        let param = |i: usize| u32::from_be_bytes([0, 1, 2, 3].map(|j| params[i * 4 + j]));
+
+        Ok(Self {
+            cipher: Cipher::try_from(cipher[0])?,
+            params: KdfParams {
+                memory_kib: param(0),
+                iterations: param(1),
+                parallelism: param(2),
+            },
+            salt: salt.try_into()?,
+            nonce: nonce.try_into()?,
+        })
+    }
+}
+
+/// Whether `data` starts like a message produced by [`encrypt`]
+#[must_use]
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase`.
+///
+/// The result holds a versioned header with the cipher, key derivation parameters, salt and
+/// nonce, followed by the ciphertext. The header is authenticated along with the message.
+pub fn encrypt(
+    plaintext: &[u8],
+    passphrase: &[u8],
+    cipher: Cipher,
+    params: &KdfParams,
+) -> Result<Vec<u8>> {
+    let mut header = Header {
+        cipher,
+        params: *params,
+        salt: [0; SALT_LENGTH],
+        nonce: [0; NONCE_LENGTH],
+    };
+    OsRng.fill_bytes(&mut header.salt);
+    OsRng.fill_bytes(&mut header.nonce);
+
+    let key = params.derive_key(passphrase, &header.salt)?;
+    let mut data = header.to_bytes();
+
+    let payload = Payload {
+        msg: plaintext,
+        aad: &data,
+    };
+    let ciphertext = match cipher {
+        Cipher::ChaCha20Poly1305 => seal::<ChaCha20Poly1305>(&key, &header.nonce, payload),
+        Cipher::Aes256Gcm => seal::<Aes256Gcm>(&key, &header.nonce, payload),
+    }?;
+
+    data.extend(ciphertext);
+
+    Ok(data)
+}
+
+/// Decrypts a message produced by [`encrypt`], failing if the passphrase is wrong or any byte
+/// of the message was changed
+pub fn decrypt(data: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    let header = Header::parse(data)?;
+    let params = header.params;
+
+    if params.memory_kib > KdfParams::MAX_MEMORY_KIB
+        || params.iterations > KdfParams::MAX_ITERATIONS
+        || params.parallelism > KdfParams::MAX_PARALLELISM
+    {
+        bail!(EncryptionError::InvalidKdfParams(params))
+    }
+
+    let (aad, ciphertext) = data.split_at(HEADER_LENGTH);
+    if ciphertext.len() < TAG_LENGTH {
+        bail!(EncryptionError::Truncated)
+    }
+
+    let key = params.derive_key(passphrase, &header.salt)?;
+
+    let payload = Payload {
+        msg: ciphertext,
+        aad,
+    };
+    match header.cipher {
+        Cipher::ChaCha20Poly1305 => open::<ChaCha20Poly1305>(&key, &header.nonce, payload),
+        Cipher::Aes256Gcm => open::<Aes256Gcm>(&key, &header.nonce, payload),
+    }
+}
+
+pub(super) fn seal<C: Aead + KeyInit>(
+    key: &[u8],
+    nonce: &[u8],
+    payload: Payload,
+) -> Result<Vec<u8>> {
+    let Ok(cipher) = C::new_from_slice(key) else {
+        bail!("Error creating cipher, invalid key length")
+    };
+
+    match cipher.encrypt(nonce.into(), payload) {
+        Ok(ciphertext) => Ok(ciphertext),
+        Err(_) => bail!("Error encrypting message"),
+    }
+}
+
+pub(super) fn open<C: Aead + KeyInit>(
+    key: &[u8],
+    nonce: &[u8],
+    payload: Payload,
+) -> Result<Vec<u8>> {
+    let Ok(cipher) = C::new_from_slice(key) else {
+        bail!("Error creating cipher, invalid key length")
+    };
+
+    match cipher.decrypt(nonce.into(), payload) {
+        Ok(plaintext) => Ok(plaintext),
+        Err(_) => bail!(EncryptionError::DecryptionFailed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NOTE: the cheapest parameters Argon2 accepts, to keep tests fast
+    const PARAMS: KdfParams = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        for cipher in [Cipher::ChaCha20Poly1305, Cipher::Aes256Gcm] {
+            let data = encrypt(b"hey", b"secret", cipher, &PARAMS).unwrap();
+
+            assert!(is_encrypted(&data));
+            assert_eq!(data.len(), HEADER_LENGTH + 3 + TAG_LENGTH);
+            assert_eq!(decrypt(&data, b"secret").unwrap(), b"hey");
+        }
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let data = encrypt(b"hey", b"secret", Cipher::default(), &PARAMS).unwrap();
+
+        let error = decrypt(&data, b"guess").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(EncryptionError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let data = encrypt(b"hey", b"secret", Cipher::Aes256Gcm, &PARAMS).unwrap();
+
+        // NOTE: the header is authenticated too, so changing the salt or the cipher text fails
+        for i in [
+            HEADER_LENGTH - NONCE_LENGTH - 1,
+            HEADER_LENGTH,
+            data.len() - 1,
+        ] {
+            let mut tampered = data.clone();
+            tampered[i] ^= 1;
+
+            let error = decrypt(&tampered, b"secret").unwrap_err();
+            assert!(matches!(
+                error.downcast_ref(),
+                Some(EncryptionError::DecryptionFailed)
+            ));
+        }
+
+        let error = decrypt(&data[..HEADER_LENGTH + 4], b"secret").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(EncryptionError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let error = decrypt(b"hey", b"secret").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(EncryptionError::NotEncrypted)
+        ));
+
+        let mut data = encrypt(b"hey", b"secret", Cipher::default(), &PARAMS).unwrap();
+        data[4] = 2;
+        let error = decrypt(&data, b"secret").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(EncryptionError::UnsupportedVersion(2))
+        ));
+
+        data[4] = VERSION;
+        data[6..10].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = decrypt(&data, b"secret").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(EncryptionError::InvalidKdfParams(_))
+        ));
+    }
+}
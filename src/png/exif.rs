@@ -0,0 +1,776 @@
+use anyhow::{bail, Result};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+use std::ops::Range;
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::{ChunkPlacement, Png};
+
+/// Typed contents of the `eXIf` chunk, a TIFF structure holding EXIF tags.
+///
+/// Tags are removed in place, so the offsets of every other tag, including the ones
+/// inside maker notes, stay valid. The bytes of removed values are zeroed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exif {
+    data: Vec<u8>,
+    byte_order: ByteOrder,
+    ifds: Vec<(Ifd, usize)>,
+    entries: Vec<ExifEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+/// The image file directories that can hold EXIF tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ifd {
+    Primary,
+    Thumbnail,
+    Exif,
+    Gps,
+    Interoperability,
+}
+
+/// A tag with its raw value, still in the byte order of the structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExifEntry {
+    ifd: Ifd,
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value: Vec<u8>,
+    byte_order: ByteOrder,
+}
+
+#[derive(Debug, ThisError)]
+pub enum ExifError {
+    #[error("Error parsing eXIf chunk, the data does not start with a TIFF header")]
+    InvalidHeader,
+    #[error("Error parsing eXIf chunk, offset {0} is out of bounds")]
+    OutOfBounds(usize),
+    #[error("Error parsing eXIf chunk, the directory at offset {0} is referenced twice")]
+    IfdLoop(usize),
+    #[error("The primary directory of an eXIf chunk can not be removed")]
+    PrimaryIfd,
+    #[error("Unknown EXIF tag {0}")]
+    UnknownTag(String),
+}
+
+/// Location of a tag in the raw data
+struct RawEntry {
+    position: usize,
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    value: Range<usize>,
+    external: bool,
+}
+
+const EXIF_POINTER: u16 = 0x8769;
+const GPS_POINTER: u16 = 0x8825;
+const INTEROPERABILITY_POINTER: u16 = 0xA005;
+const THUMBNAIL_OFFSET: u16 = 0x0201;
+const THUMBNAIL_LENGTH: u16 = 0x0202;
+
+const ENTRY_LENGTH: usize = 12;
+
+const IMAGE_TAGS: &[(u16, &str)] = &[
+    (0x0100, "ImageWidth"),
+    (0x0101, "ImageLength"),
+    (0x0103, "Compression"),
+    (0x010E, "ImageDescription"),
+    (0x010F, "Make"),
+    (0x0110, "Model"),
+    (0x0112, "Orientation"),
+    (0x011A, "XResolution"),
+    (0x011B, "YResolution"),
+    (0x0128, "ResolutionUnit"),
+    (0x0131, "Software"),
+    (0x0132, "DateTime"),
+    (0x013B, "Artist"),
+    (0x0201, "JPEGInterchangeFormat"),
+    (0x0202, "JPEGInterchangeFormatLength"),
+    (0x0213, "YCbCrPositioning"),
+    (0x8298, "Copyright"),
+    (0x8769, "ExifIFDPointer"),
+    (0x8825, "GPSInfoIFDPointer"),
+];
+
+const EXIF_TAGS: &[(u16, &str)] = &[
+    (0x829A, "ExposureTime"),
+    (0x829D, "FNumber"),
+    (0x8822, "ExposureProgram"),
+    (0x8827, "PhotographicSensitivity"),
+    (0x9000, "ExifVersion"),
+    (0x9003, "DateTimeOriginal"),
+    (0x9004, "DateTimeDigitized"),
+    (0x9010, "OffsetTime"),
+    (0x9011, "OffsetTimeOriginal"),
+    (0x9012, "OffsetTimeDigitized"),
+    (0x9101, "ComponentsConfiguration"),
+    (0x9201, "ShutterSpeedValue"),
+    (0x9202, "ApertureValue"),
+    (0x9204, "ExposureBiasValue"),
+    (0x9207, "MeteringMode"),
+    (0x9209, "Flash"),
+    (0x920A, "FocalLength"),
+    (0x927C, "MakerNote"),
+    (0x9286, "UserComment"),
+    (0x9290, "SubSecTime"),
+    (0x9291, "SubSecTimeOriginal"),
+    (0x9292, "SubSecTimeDigitized"),
+    (0xA000, "FlashpixVersion"),
+    (0xA001, "ColorSpace"),
+    (0xA002, "PixelXDimension"),
+    (0xA003, "PixelYDimension"),
+    (0xA005, "InteroperabilityIFDPointer"),
+    (0xA402, "ExposureMode"),
+    (0xA403, "WhiteBalance"),
+    (0xA405, "FocalLengthIn35mmFilm"),
+    (0xA406, "SceneCaptureType"),
+    (0xA420, "ImageUniqueID"),
+    (0xA430, "CameraOwnerName"),
+    (0xA431, "BodySerialNumber"),
+    (0xA433, "LensMake"),
+    (0xA434, "LensModel"),
+    (0xA435, "LensSerialNumber"),
+];
+
+const GPS_TAGS: &[(u16, &str)] = &[
+    (0x0000, "GPSVersionID"),
+    (0x0001, "GPSLatitudeRef"),
+    (0x0002, "GPSLatitude"),
+    (0x0003, "GPSLongitudeRef"),
+    (0x0004, "GPSLongitude"),
+    (0x0005, "GPSAltitudeRef"),
+    (0x0006, "GPSAltitude"),
+    (0x0007, "GPSTimeStamp"),
+    (0x000C, "GPSSpeedRef"),
+    (0x000D, "GPSSpeed"),
+    (0x0010, "GPSImgDirectionRef"),
+    (0x0011, "GPSImgDirection"),
+    (0x0012, "GPSMapDatum"),
+    (0x001D, "GPSDateStamp"),
+    (0x001F, "GPSHPositioningError"),
+];
+
+const INTEROPERABILITY_TAGS: &[(u16, &str)] = &[
+    (0x0001, "InteroperabilityIndex"),
+    (0x0002, "InteroperabilityVersion"),
+];
+
+impl Exif {
+    /// Parses the chunk data, tolerating the `Exif\0\0` prefix some writers add
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data = data.strip_prefix(b"Exif\0\0").unwrap_or(data);
+
+        let byte_order = match data.get(..4) {
+            Some(b"II*\0") => ByteOrder::LittleEndian,
+            Some(b"MM\0*") => ByteOrder::BigEndian,
+            _ => bail!(ExifError::InvalidHeader),
+        };
+
+        let mut exif = Self {
+            data: data.to_vec(),
+            byte_order,
+            ifds: Vec::new(),
+            entries: Vec::new(),
+        };
+        exif.parse()?;
+
+        Ok(exif)
+    }
+
+    #[must_use]
+    pub const fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[ExifEntry] {
+        &self.entries
+    }
+
+    #[must_use]
+    pub fn entry(&self, ifd: Ifd, tag: u16) -> Option<&ExifEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.ifd == ifd && entry.tag == tag)
+    }
+
+    /// Removes a tag, returning whether it was present.
+    ///
+    /// Removing the pointer to another directory removes that whole directory.
+    pub fn remove_tag(&mut self, ifd: Ifd, tag: u16) -> Result<bool> {
+        let Some(&(_, offset)) = self.ifds.iter().find(|(found, _)| *found == ifd) else {
+            return Ok(false);
+        };
+
+        let mut entries = self.raw_entries(offset)?;
+        let Some(index) = entries.iter().position(|entry| entry.tag == tag) else {
+            return Ok(false);
+        };
+        let removed = entries.remove(index);
+
+        self.wipe_value(ifd, &removed)?;
+
+        // NOTE: the table is rewritten in place, shifting the following entries
+        // and moving the next directory offset back by one entry
+        let next = self.u32_at(offset + 2 + (entries.len() + 1) * ENTRY_LENGTH)?;
+        let table: Vec<u8> = entries
+            .iter()
+            .flat_map(|entry| self.data[entry.position..entry.position + ENTRY_LENGTH].to_vec())
+            .collect();
+
+        let count = u16::try_from(entries.len())?;
+        self.write(offset, &self.byte_order.u16_bytes(count))?;
+        self.write(offset + 2, &table)?;
+        self.write(offset + 2 + table.len(), &self.byte_order.u32_bytes(next))?;
+        self.write(offset + 6 + table.len(), &[0; ENTRY_LENGTH])?;
+
+        self.parse()?;
+
+        Ok(true)
+    }
+
+    /// Removes a whole directory, such as every GPS tag, returning whether it was present
+    pub fn remove_ifd(&mut self, ifd: Ifd) -> Result<bool> {
+        match ifd {
+            Ifd::Primary => bail!(ExifError::PrimaryIfd),
+            Ifd::Thumbnail => {
+                let Some(&(_, offset)) = self.ifds.iter().find(|(found, _)| *found == ifd) else {
+                    return Ok(false);
+                };
+                let (_, primary) = self.ifds[0];
+                let next = primary + 2 + self.raw_entries(primary)?.len() * ENTRY_LENGTH;
+
+                self.wipe_ifd(ifd, offset)?;
+                self.write(next, &[0; 4])?;
+                self.parse()?;
+
+                Ok(true)
+            }
+            Ifd::Exif => self.remove_tag(Ifd::Primary, EXIF_POINTER),
+            Ifd::Gps => self.remove_tag(Ifd::Primary, GPS_POINTER),
+            Ifd::Interoperability => self.remove_tag(Ifd::Exif, INTEROPERABILITY_POINTER),
+        }
+    }
+
+    /// Removes a tag by its name, as listed by [`tag_name`]
+    pub fn remove_tag_by_name(&mut self, name: &str) -> Result<bool> {
+        let Some((ifds, tag)) = tag_by_name(name) else {
+            bail!(ExifError::UnknownTag(name.to_string()));
+        };
+
+        let mut removed = false;
+        for &ifd in ifds {
+            removed |= self.remove_tag(ifd, tag)?;
+        }
+
+        Ok(removed)
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::EXIF, self.data.clone())
+    }
+
+    fn parse(&mut self) -> Result<()> {
+        self.ifds.clear();
+        self.entries.clear();
+
+        let mut pending = VecDeque::from([(Ifd::Primary, self.u32_at(4)? as usize)]);
+        let mut visited = HashSet::new();
+
+        while let Some((ifd, offset)) = pending.pop_front() {
+            if !visited.insert(offset) {
+                bail!(ExifError::IfdLoop(offset));
+            }
+            self.ifds.push((ifd, offset));
+
+            let entries = self.raw_entries(offset)?;
+
+            for entry in &entries {
+                if let Some(child) = child_ifd(ifd, entry.tag) {
+                    pending.push_back((child, self.u32_at(entry.position + 8)? as usize));
+                }
+
+                self.entries.push(ExifEntry {
+                    ifd,
+                    tag: entry.tag,
+                    field_type: entry.field_type,
+                    count: entry.count,
+                    value: self.data[entry.value.clone()].to_vec(),
+                    byte_order: self.byte_order,
+                });
+            }
+
+            let next = self.u32_at(offset + 2 + entries.len() * ENTRY_LENGTH)? as usize;
+            if ifd == Ifd::Primary && next != 0 {
+                pending.push_back((Ifd::Thumbnail, next));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn raw_entries(&self, offset: usize) -> Result<Vec<RawEntry>> {
+        let count = self.u16_at(offset)?;
+
+        (0..usize::from(count))
+            .map(|i| {
+                let position = offset + 2 + i * ENTRY_LENGTH;
+                let field_type = self.u16_at(position + 2)?;
+                let count = self.u32_at(position + 4)?;
+
+                let size = type_size(field_type).and_then(|size| size.checked_mul(count as usize));
+                let (value, external) = match size {
+                    Some(size) if size > 4 => {
+                        let start = self.u32_at(position + 8)? as usize;
+                        (start..start.saturating_add(size), true)
+                    }
+                    Some(size) => (position + 8..position + 8 + size, false),
+                    None => (position + 8..position + 12, false),
+                };
+
+                if value.end > self.data.len() {
+                    bail!(ExifError::OutOfBounds(value.end));
+                }
+
+                Ok(RawEntry {
+                    position,
+                    tag: self.u16_at(position)?,
+                    field_type,
+                    count,
+                    value,
+                    external,
+                })
+            })
+            .collect()
+    }
+
+    /// Zeroes the bytes of a value stored outside its entry and any directory it points to
+    fn wipe_value(&mut self, ifd: Ifd, entry: &RawEntry) -> Result<()> {
+        if entry.external {
+            self.data[entry.value.clone()].fill(0);
+        }
+
+        if let Some(child) = child_ifd(ifd, entry.tag) {
+            let offset = self.u32_at(entry.position + 8)? as usize;
+            self.wipe_ifd(child, offset)?;
+        }
+
+        Ok(())
+    }
+
+    fn wipe_ifd(&mut self, ifd: Ifd, offset: usize) -> Result<()> {
+        let entries = self.raw_entries(offset)?;
+
+        for entry in &entries {
+            self.wipe_value(ifd, entry)?;
+        }
+
+        let thumbnail = |tag| {
+            entries
+                .iter()
+                .find(|entry| entry.tag == tag)
+                .map(|entry| self.u32_at(entry.position + 8))
+                .transpose()
+        };
+        if let (Some(start), Some(length)) =
+            (thumbnail(THUMBNAIL_OFFSET)?, thumbnail(THUMBNAIL_LENGTH)?)
+        {
+            let (start, length) = (start as usize, length as usize);
+            if let Some(bytes) = self.data.get_mut(start..start + length) {
+                bytes.fill(0);
+            }
+        }
+
+        let length = 2 + entries.len() * ENTRY_LENGTH + 4;
+        self.write(offset, &vec![0; length])
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16> {
+        match self.data.get(offset..offset + 2) {
+            Some(&[a, b]) => Ok(self.byte_order.u16([a, b])),
+            _ => bail!(ExifError::OutOfBounds(offset)),
+        }
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32> {
+        match self.data.get(offset..offset + 4) {
+            Some(&[a, b, c, d]) => Ok(self.byte_order.u32([a, b, c, d])),
+            _ => bail!(ExifError::OutOfBounds(offset)),
+        }
+    }
+
+    fn write(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        let Some(target) = self.data.get_mut(offset..offset + bytes.len()) else {
+            bail!(ExifError::OutOfBounds(offset));
+        };
+        target.copy_from_slice(bytes);
+
+        Ok(())
+    }
+}
+
+impl ByteOrder {
+    const fn u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::LittleEndian => u16::from_le_bytes(bytes),
+            Self::BigEndian => u16::from_be_bytes(bytes),
+        }
+    }
+
+    const fn u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Self::LittleEndian => u32::from_le_bytes(bytes),
+            Self::BigEndian => u32::from_be_bytes(bytes),
+        }
+    }
+
+    const fn u16_bytes(self, value: u16) -> [u8; 2] {
+        match self {
+            Self::LittleEndian => value.to_le_bytes(),
+            Self::BigEndian => value.to_be_bytes(),
+        }
+    }
+
+    const fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            Self::LittleEndian => value.to_le_bytes(),
+            Self::BigEndian => value.to_be_bytes(),
+        }
+    }
+}
+
+impl ExifEntry {
+    #[must_use]
+    pub const fn ifd(&self) -> Ifd {
+        self.ifd
+    }
+
+    #[must_use]
+    pub const fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    #[must_use]
+    pub fn name(&self) -> Option<&'static str> {
+        tag_name(self.ifd, self.tag)
+    }
+
+    #[must_use]
+    pub const fn field_type(&self) -> u16 {
+        self.field_type
+    }
+
+    #[must_use]
+    pub const fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Raw bytes of the value, in the byte order of the structure
+    #[must_use]
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Human readable value, strings for ASCII tags and numbers for numeric ones
+    #[must_use]
+    pub fn value_string(&self) -> String {
+        const SHOWN: usize = 8;
+
+        let order = self.byte_order;
+        let numbers: Vec<String> = match self.field_type {
+            2 => {
+                let text = self.value.split(|&b| b == 0).next().unwrap_or_default();
+                return String::from_utf8_lossy(text).into_owned();
+            }
+            3 | 8 => self
+                .value
+                .chunks_exact(2)
+                .map(|b| {
+                    let value = order.u16([b[0], b[1]]);
+                    if self.field_type == 8 {
+                        value.cast_signed().to_string()
+                    } else {
+                        value.to_string()
+                    }
+                })
+                .collect(),
+            4 | 9 => self
+                .value
+                .chunks_exact(4)
+                .map(|b| {
+                    let value = order.u32([b[0], b[1], b[2], b[3]]);
+                    if self.field_type == 9 {
+                        value.cast_signed().to_string()
+                    } else {
+                        value.to_string()
+                    }
+                })
+                .collect(),
+            5 | 10 => self
+                .value
+                .chunks_exact(8)
+                .map(|b| {
+                    let numerator = order.u32([b[0], b[1], b[2], b[3]]);
+                    let denominator = order.u32([b[4], b[5], b[6], b[7]]);
+                    if self.field_type == 10 {
+                        format!("{}/{}", numerator.cast_signed(), denominator.cast_signed())
+                    } else {
+                        format!("{numerator}/{denominator}")
+                    }
+                })
+                .collect(),
+            1 | 6 | 7 if self.value.len() <= SHOWN => self
+                .value
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            _ => return format!("{} bytes", self.value.len()),
+        };
+
+        if numbers.len() > SHOWN {
+            format!("{}, ...", numbers[..SHOWN].join(", "))
+        } else {
+            numbers.join(", ")
+        }
+    }
+}
+
+impl Display for ExifEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}")?,
+            None => write!(f, "{:?} tag 0x{:04X}", self.ifd, self.tag)?,
+        }
+
+        write!(f, ": {}", self.value_string())
+    }
+}
+
+/// Name of a known tag of the given directory
+#[must_use]
+pub fn tag_name(ifd: Ifd, tag: u16) -> Option<&'static str> {
+    ifd_tags(ifd)
+        .iter()
+        .find(|(code, _)| *code == tag)
+        .map(|(_, name)| *name)
+}
+
+/// Tag code of a known tag name with the directories it can be found in
+#[must_use]
+pub fn tag_by_name(name: &str) -> Option<(&'static [Ifd], u16)> {
+    const IMAGE: &[Ifd] = &[Ifd::Primary, Ifd::Thumbnail];
+
+    [
+        (IMAGE, IMAGE_TAGS),
+        (&[Ifd::Exif][..], EXIF_TAGS),
+        (&[Ifd::Gps][..], GPS_TAGS),
+        (&[Ifd::Interoperability][..], INTEROPERABILITY_TAGS),
+    ]
+    .into_iter()
+    .find_map(|(ifds, tags)| {
+        tags.iter()
+            .find(|(_, tag_name)| tag_name.eq_ignore_ascii_case(name))
+            .map(|(tag, _)| (ifds, *tag))
+    })
+}
+
+const fn ifd_tags(ifd: Ifd) -> &'static [(u16, &'static str)] {
+    match ifd {
+        Ifd::Primary | Ifd::Thumbnail => IMAGE_TAGS,
+        Ifd::Exif => EXIF_TAGS,
+        Ifd::Gps => GPS_TAGS,
+        Ifd::Interoperability => INTEROPERABILITY_TAGS,
+    }
+}
+
+const fn child_ifd(ifd: Ifd, tag: u16) -> Option<Ifd> {
+    match (ifd, tag) {
+        (Ifd::Primary | Ifd::Thumbnail, EXIF_POINTER) => Some(Ifd::Exif),
+        (Ifd::Primary | Ifd::Thumbnail, GPS_POINTER) => Some(Ifd::Gps),
+        (Ifd::Exif, INTEROPERABILITY_POINTER) => Some(Ifd::Interoperability),
+        _ => None,
+    }
+}
+
+/// Size in bytes of one value of a TIFF field type, `None` for unknown types
+const fn type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+impl Png {
+    pub fn exif(&self) -> Result<Option<Exif>> {
+        self.chunks
+            .iter()
+            .find(|chunk| *chunk.chunk_type() == ChunkType::EXIF)
+            .map(|chunk| Exif::from_data(chunk.data()))
+            .transpose()
+    }
+
+    /// Replaces the `eXIf` chunk in place, or adds it before the image data
+    pub fn set_exif(&mut self, exif: &Exif) {
+        let chunk = exif.to_chunk();
+
+        match self
+            .chunks
+            .iter()
+            .position(|chunk| *chunk.chunk_type() == ChunkType::EXIF)
+        {
+            Some(index) => self.chunks[index] = chunk,
+            None => self.insert_chunk(chunk, ChunkPlacement::BeforeFirstIdat),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    const LATITUDE: [u32; 6] = [48, 1, 51, 1, 2999, 100];
+
+    /// Primary directory with Make and Orientation, an Exif directory with the date
+    /// and a GPS directory with the latitude
+    fn tiff(order: ByteOrder) -> Vec<u8> {
+        let u16b = |value| order.u16_bytes(value).to_vec();
+        let u32b = |value| order.u32_bytes(value).to_vec();
+        let entry = |tag, field_type, count, value: Vec<u8>| {
+            [u16b(tag), u16b(field_type), u32b(count), value].concat()
+        };
+
+        let mut data = match order {
+            ByteOrder::LittleEndian => b"II*\0".to_vec(),
+            ByteOrder::BigEndian => b"MM\0*".to_vec(),
+        };
+        data.extend(u32b(8));
+
+        data.extend(u16b(4));
+        data.extend(entry(0x010F, 2, 6, u32b(62)));
+        data.extend(entry(0x0112, 3, 1, [u16b(1), vec![0, 0]].concat()));
+        data.extend(entry(EXIF_POINTER, 4, 1, u32b(68)));
+        data.extend(entry(GPS_POINTER, 4, 1, u32b(106)));
+        data.extend(u32b(0));
+        data.extend(b"Phone\0");
+
+        data.extend(u16b(1));
+        data.extend(entry(0x9003, 2, 20, u32b(86)));
+        data.extend(u32b(0));
+        data.extend(b"2024:01:02 03:04:05\0");
+
+        data.extend(u16b(2));
+        data.extend(entry(0x0001, 2, 2, b"N\0\0\0".to_vec()));
+        data.extend(entry(0x0002, 5, 3, u32b(136)));
+        data.extend(u32b(0));
+        data.extend(LATITUDE.iter().flat_map(|&value| u32b(value)));
+
+        data
+    }
+
+    fn names(exif: &Exif) -> Vec<&'static str> {
+        exif.entries().iter().filter_map(ExifEntry::name).collect()
+    }
+
+    #[test]
+    fn test_parse_both_byte_orders() {
+        for order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let exif = Exif::from_data(&tiff(order)).unwrap();
+
+            assert_eq!(exif.byte_order(), order);
+            assert_eq!(
+                names(&exif),
+                [
+                    "Make",
+                    "Orientation",
+                    "ExifIFDPointer",
+                    "GPSInfoIFDPointer",
+                    "DateTimeOriginal",
+                    "GPSLatitudeRef",
+                    "GPSLatitude"
+                ]
+            );
+
+            let value = |ifd, tag| exif.entry(ifd, tag).unwrap().value_string();
+            assert_eq!(value(Ifd::Primary, 0x010F), "Phone");
+            assert_eq!(value(Ifd::Primary, 0x0112), "1");
+            assert_eq!(value(Ifd::Gps, 0x0002), "48/1, 51/1, 2999/100");
+            assert_eq!(value(Ifd::Exif, 0x9003), "2024:01:02 03:04:05");
+        }
+    }
+
+    #[test]
+    fn test_remove_gps() {
+        for order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let mut exif = Exif::from_data(&tiff(order)).unwrap();
+
+            assert!(exif.remove_ifd(Ifd::Gps).unwrap());
+            assert!(!exif.remove_ifd(Ifd::Gps).unwrap());
+
+            assert_eq!(
+                names(&exif),
+                ["Make", "Orientation", "ExifIFDPointer", "DateTimeOriginal"]
+            );
+            assert!(exif.data[106..].iter().all(|&b| b == 0));
+
+            let reparsed = Exif::from_data(exif.to_chunk().data()).unwrap();
+            assert_eq!(reparsed, exif);
+        }
+    }
+
+    #[test]
+    fn test_remove_tag_by_name() {
+        let mut exif = Exif::from_data(&tiff(ByteOrder::BigEndian)).unwrap();
+
+        assert!(exif.remove_tag_by_name("make").unwrap());
+        assert!(exif.remove_tag_by_name("DateTimeOriginal").unwrap());
+        assert!(exif.remove_tag_by_name("Nonexistent").is_err());
+
+        assert!(!exif.data.windows(5).any(|window| window == b"Phone"));
+        assert!(!exif.data.windows(4).any(|window| window == b"2024"));
+        assert!(exif.entry(Ifd::Primary, 0x0112).is_some());
+        assert!(exif.entry(Ifd::Gps, 0x0002).is_some());
+    }
+
+    #[test]
+    fn test_invalid_exif() {
+        assert!(Exif::from_data(b"not tiff").is_err());
+
+        let mut data = tiff(ByteOrder::LittleEndian);
+        data[4] = 200;
+        assert!(Exif::from_data(&data).is_err());
+
+        // NOTE: the GPS pointer is changed to point back to the primary directory
+        let mut data = tiff(ByteOrder::LittleEndian);
+        data[54..58].copy_from_slice(&8_u32.to_le_bytes());
+        assert!(Exif::from_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_png_exif() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.exif().unwrap().is_none());
+
+        let mut exif = Exif::from_data(&tiff(ByteOrder::LittleEndian)).unwrap();
+        png.set_exif(&exif);
+        exif.remove_ifd(Ifd::Gps).unwrap();
+        png.set_exif(&exif);
+
+        let chunk = png.chunk_by_type("eXIf").unwrap();
+        assert!(Chunk::try_from(chunk.as_bytes().as_ref()).is_ok());
+        assert_eq!(png.exif().unwrap(), Some(exif));
+        assert_eq!(png.chunks().len(), 8);
+    }
+}
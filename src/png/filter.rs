@@ -0,0 +1,231 @@
+use anyhow::{bail, Error, Result};
+use thiserror::Error as ThisError;
+
+/// The five filter types PNG applies to each scanline before compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    None = 0,
+    Sub = 1,
+    Up = 2,
+    Average = 3,
+    Paeth = 4,
+}
+
+#[derive(Debug, ThisError)]
+pub enum FilterError {
+    #[error("Error unfiltering image data, unknown filter type {0}")]
+    InvalidFilterType(u8),
+    #[error("Error unfiltering image data, expected {0} bytes but found {1}")]
+    InvalidDataLength(usize, usize),
+}
+
+impl TryFrom<u8> for FilterType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::None,
+            1 => Self::Sub,
+            2 => Self::Up,
+            3 => Self::Average,
+            4 => Self::Paeth,
+            _ => bail!(FilterError::InvalidFilterType(value)),
+        })
+    }
+}
+
+/// Reverses the filter of every scanline of `data`, where each scanline is a filter type byte
+/// followed by `stride` bytes, and `bpp` is the number of bytes per complete pixel (at least 1)
+pub fn unfilter(data: &[u8], stride: usize, bpp: usize) -> Result<Vec<u8>> {
+    if stride == 0 {
+        return Ok(Vec::new());
+    }
+
+    if !data.len().is_multiple_of(stride + 1) {
+        let expected = (data.len() / (stride + 1) + 1) * (stride + 1);
+        bail!(FilterError::InvalidDataLength(expected, data.len()));
+    }
+
+    let height = data.len() / (stride + 1);
+    let mut pixels = vec![0; stride * height];
+    let mut previous = vec![0; stride];
+
+    for (line, row) in data
+        .chunks_exact(stride + 1)
+        .zip(pixels.chunks_exact_mut(stride))
+    {
+        let (&filter_type, line) = line.split_first().unwrap_or((&0, &[]));
+        let filter_type = FilterType::try_from(filter_type)?;
+
+        for i in 0..stride {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = previous[i];
+            let c = if i >= bpp { previous[i - bpp] } else { 0 };
+
+            row[i] = line[i].wrapping_add(predict(filter_type, a, b, c));
+        }
+
+        previous.copy_from_slice(row);
+    }
+
+    Ok(pixels)
+}
+
+/// How the encoder picks the filter type of each scanline
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilterStrategy {
+    /// Use the same filter type for every scanline
+    Fixed(FilterType),
+    /// Pick for each scanline the filter type with the smallest sum of absolute differences
+    #[default]
+    Adaptive,
+}
+
+/// Filters every scanline of `pixels`, prefixing each one with its filter type byte
+#[must_use]
+pub fn filter(pixels: &[u8], stride: usize, bpp: usize, strategy: FilterStrategy) -> Vec<u8> {
+    if stride == 0 {
+        return Vec::new();
+    }
+
+    let mut data = Vec::with_capacity(pixels.len() + pixels.len() / stride);
+    let mut line = vec![0; stride];
+    let empty = vec![0; stride];
+    let mut previous: &[u8] = &empty;
+
+    for row in pixels.chunks_exact(stride) {
+        let filter_type = match strategy {
+            FilterStrategy::Fixed(filter_type) => filter_type,
+            FilterStrategy::Adaptive => [
+                FilterType::None,
+                FilterType::Sub,
+                FilterType::Up,
+                FilterType::Average,
+                FilterType::Paeth,
+            ]
+            .into_iter()
+            .min_by_key(|&filter_type| {
+                filter_line(filter_type, row, previous, bpp, &mut line);
+                line.iter()
+                    .map(|&byte| u64::from(byte.cast_signed().unsigned_abs()))
+                    .sum::<u64>()
+            })
+            .unwrap_or(FilterType::None),
+        };
+
+        filter_line(filter_type, row, previous, bpp, &mut line);
+        data.push(filter_type as u8);
+        data.extend_from_slice(&line);
+
+        previous = row;
+    }
+
+    data
+}
+
+fn filter_line(filter_type: FilterType, row: &[u8], previous: &[u8], bpp: usize, line: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = previous[i];
+        let c = if i >= bpp { previous[i - bpp] } else { 0 };
+
+        line[i] = row[i].wrapping_sub(predict(filter_type, a, b, c));
+    }
+}
+
+/// Value predicted for a byte from its left `a`, up `b` and up left `c` neighbours
+const fn predict(filter_type: FilterType, a: u8, b: u8, c: u8) -> u8 {
+    match filter_type {
+        FilterType::None => 0,
+        FilterType::Sub => a,
+        FilterType::Up => b,
+        FilterType::Average => u8::midpoint(a, b),
+        FilterType::Paeth => paeth(a, b, c),
+    }
+}
+
+const fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unfilter_each_type() {
+        #[rustfmt::skip]
+        let data = [
+            0, 10, 20, 30, 40,
+            1, 10, 20, 5, 5,
+            2, 1, 1, 1, 1,
+            3, 2, 2, 2, 2,
+            4, 1, 1, 1, 1,
+        ];
+
+        let pixels = unfilter(&data, 4, 2).unwrap();
+
+        #[rustfmt::skip]
+        let expected = [
+            10, 20, 30, 40,
+            10, 20, 15, 25,
+            11, 21, 16, 26,
+            7, 12, 13, 21,
+            8, 13, 14, 22,
+        ];
+
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn test_unfilter_invalid_filter_type() {
+        assert!(unfilter(&[5, 1, 2], 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_unfilter_invalid_length() {
+        assert!(unfilter(&[0, 1, 2, 0], 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_filter_round_trip() {
+        let pixels: Vec<u8> = (0..=255).cycle().step_by(7).take(6 * 8).collect();
+
+        for strategy in [
+            FilterStrategy::Fixed(FilterType::None),
+            FilterStrategy::Fixed(FilterType::Sub),
+            FilterStrategy::Fixed(FilterType::Up),
+            FilterStrategy::Fixed(FilterType::Average),
+            FilterStrategy::Fixed(FilterType::Paeth),
+            FilterStrategy::Adaptive,
+        ] {
+            let data = filter(&pixels, 6, 3, strategy);
+            assert_eq!(data.len(), pixels.len() + 8);
+            assert_eq!(unfilter(&data, 6, 3).unwrap(), pixels);
+        }
+    }
+
+    #[test]
+    fn test_filter_fixed_type_bytes() {
+        let data = filter(&[1, 2, 3, 4], 2, 1, FilterStrategy::Fixed(FilterType::Up));
+        assert_eq!(data, [2, 1, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_paeth() {
+        assert_eq!(paeth(10, 20, 10), 20);
+        assert_eq!(paeth(20, 10, 10), 20);
+        assert_eq!(paeth(10, 10, 20), 10);
+    }
+}
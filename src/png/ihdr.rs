@@ -0,0 +1,361 @@
+use anyhow::{bail, Error, Result};
+use std::fmt::Display;
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::PngError;
+
+/// Typed contents of the `IHDR` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ihdr {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    compression_method: u8,
+    filter_method: u8,
+    interlace_method: InterlaceMethod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterlaceMethod {
+    None = 0,
+    Adam7 = 1,
+}
+
+#[derive(Debug, ThisError)]
+pub enum IhdrError {
+    #[error("the image has no IHDR chunk")]
+    Missing,
+    #[error("IHDR data must be 13 bytes long but it is {0}")]
+    InvalidLength(usize),
+    #[error("image dimensions {0}x{1} must be between 1 and 2^31-1")]
+    InvalidDimensions(u32, u32),
+    #[error("color type {0} is not valid")]
+    InvalidColorType(u8),
+    #[error("bit depth {0} is not allowed for color type {1:?}")]
+    InvalidBitDepth(u8, ColorType),
+    #[error("compression method {0} is not valid")]
+    InvalidCompressionMethod(u8),
+    #[error("filter method {0} is not valid")]
+    InvalidFilterMethod(u8),
+    #[error("interlace method {0} is not valid")]
+    InvalidInterlaceMethod(u8),
+}
+
+impl Ihdr {
+    pub const DATA_LENGTH: usize = 13;
+    pub const MAX_DIMENSION: u32 = (1 << 31) - 1;
+
+    /// Creates a validated header using the only compression and filter methods defined
+    pub fn new(
+        width: u32,
+        height: u32,
+        bit_depth: u8,
+        color_type: ColorType,
+        interlace_method: InterlaceMethod,
+    ) -> Result<Self> {
+        let ihdr = Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method,
+        };
+
+        ihdr.validate()?;
+
+        Ok(ihdr)
+    }
+
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[must_use]
+    pub const fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    #[must_use]
+    pub const fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    #[must_use]
+    pub const fn compression_method(&self) -> u8 {
+        self.compression_method
+    }
+
+    #[must_use]
+    pub const fn filter_method(&self) -> u8 {
+        self.filter_method
+    }
+
+    #[must_use]
+    pub const fn interlace_method(&self) -> InterlaceMethod {
+        self.interlace_method
+    }
+
+    /// Parses and validates the data of an `IHDR` chunk
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let data: [u8; Self::DATA_LENGTH] = match data.try_into() {
+            Ok(data) => data,
+            Err(_) => bail!(PngError::InvalidIhdr(IhdrError::InvalidLength(data.len()))),
+        };
+
+        let ihdr = Self {
+            width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            bit_depth: data[8],
+            color_type: ColorType::try_from(data[9])?,
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: InterlaceMethod::try_from(data[12])?,
+        };
+
+        ihdr.validate()?;
+
+        Ok(ihdr)
+    }
+
+    /// Checks the dimensions, the bit depth against the color type and the methods
+    fn validate(&self) -> Result<()> {
+        let invalid = |error| Err(PngError::InvalidIhdr(error).into());
+
+        if !(1..=Self::MAX_DIMENSION).contains(&self.width)
+            || !(1..=Self::MAX_DIMENSION).contains(&self.height)
+        {
+            return invalid(IhdrError::InvalidDimensions(self.width, self.height));
+        }
+
+        if !self
+            .color_type
+            .allowed_bit_depths()
+            .contains(&self.bit_depth)
+        {
+            return invalid(IhdrError::InvalidBitDepth(self.bit_depth, self.color_type));
+        }
+
+        if self.compression_method != 0 {
+            return invalid(IhdrError::InvalidCompressionMethod(self.compression_method));
+        }
+
+        if self.filter_method != 0 {
+            return invalid(IhdrError::InvalidFilterMethod(self.filter_method));
+        }
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub const fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+
+    /// Bytes needed by a scanline of `width` pixels, without its filter type byte
+    #[must_use]
+    pub const fn stride(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    #[must_use]
+    pub fn to_data(&self) -> Vec<u8> {
+        self.width
+            .to_be_bytes()
+            .iter()
+            .chain(self.height.to_be_bytes().iter())
+            .chain(
+                [
+                    self.bit_depth,
+                    self.color_type as u8,
+                    self.compression_method,
+                    self.filter_method,
+                    self.interlace_method as u8,
+                ]
+                .iter(),
+            )
+            .copied()
+            .collect()
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(ChunkType::IHDR, self.to_data())
+    }
+}
+
+impl ColorType {
+    #[must_use]
+    pub const fn channels(self) -> usize {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+
+    #[must_use]
+    pub const fn allowed_bit_depths(self) -> &'static [u8] {
+        match self {
+            Self::Grayscale => &[1, 2, 4, 8, 16],
+            Self::Indexed => &[1, 2, 4, 8],
+            Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => &[8, 16],
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::Grayscale,
+            2 => Self::Rgb,
+            3 => Self::Indexed,
+            4 => Self::GrayscaleAlpha,
+            6 => Self::Rgba,
+            _ => bail!(PngError::InvalidIhdr(IhdrError::InvalidColorType(value))),
+        })
+    }
+}
+
+impl TryFrom<u8> for InterlaceMethod {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Ok(match value {
+            0 => Self::None,
+            1 => Self::Adam7,
+            _ => bail!(PngError::InvalidIhdr(IhdrError::InvalidInterlaceMethod(
+                value
+            ))),
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = Error;
+
+    fn try_from(value: &Chunk) -> Result<Self> {
+        if *value.chunk_type() != ChunkType::IHDR {
+            bail!(PngError::InvalidIhdr(IhdrError::Missing));
+        }
+
+        Self::from_data(value.data())
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {:?}",
+            self.width, self.height, self.bit_depth, self.color_type
+        )?;
+
+        if self.interlace_method == InterlaceMethod::Adam7 {
+            write!(f, ", interlaced")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+    use crate::png::Png;
+
+    fn testing_ihdr() -> Ihdr {
+        Ihdr::new(50, 50, 8, ColorType::Rgba, InterlaceMethod::None).unwrap()
+    }
+
+    #[test]
+    fn test_ihdr_from_png() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.ihdr().unwrap(), testing_ihdr());
+    }
+
+    #[test]
+    fn test_ihdr_round_trip() {
+        let ihdr = testing_ihdr();
+        let chunk = ihdr.to_chunk();
+        assert_eq!(Ihdr::try_from(&chunk).unwrap(), ihdr);
+    }
+
+    #[test]
+    fn test_ihdr_zero_dimension() {
+        assert!(Ihdr::new(0, 50, 8, ColorType::Rgba, InterlaceMethod::None).is_err());
+
+        let mut data = testing_ihdr().to_data();
+        data[4..8].copy_from_slice(&[0, 0, 0, 0]);
+        assert!(Ihdr::from_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_invalid_bit_depth() {
+        let error = Ihdr::new(50, 50, 4, ColorType::Rgba, InterlaceMethod::None).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<PngError>(),
+            Some(PngError::InvalidIhdr(IhdrError::InvalidBitDepth(
+                4,
+                ColorType::Rgba
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_ihdr_invalid_color_type() {
+        let mut data = testing_ihdr().to_data();
+        data[9] = 5;
+        assert!(Ihdr::from_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_invalid_methods() {
+        let mut data = testing_ihdr().to_data();
+        data[10] = 1;
+        assert!(Ihdr::from_data(&data).is_err());
+
+        let mut data = testing_ihdr().to_data();
+        data[12] = 2;
+        assert!(Ihdr::from_data(&data).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_stride() {
+        assert_eq!(testing_ihdr().stride(50), 200);
+
+        let ihdr = Ihdr::new(3, 1, 1, ColorType::Grayscale, InterlaceMethod::None).unwrap();
+        assert_eq!(ihdr.stride(3), 1);
+
+        let ihdr = Ihdr::new(3, 1, 16, ColorType::Rgb, InterlaceMethod::None).unwrap();
+        assert_eq!(ihdr.stride(3), 18);
+    }
+
+    #[test]
+    fn test_ihdr_invalid_length() {
+        let data = testing_ihdr().to_data();
+        assert!(Ihdr::from_data(&data[..12]).is_err());
+    }
+}
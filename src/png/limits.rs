@@ -0,0 +1,135 @@
+use anyhow::{bail, Result};
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::ihdr::Ihdr;
+
+/// Resource limits applied while parsing and inflating untrusted images.
+///
+/// The defaults are generous for real images but stop inputs crafted to exhaust memory.
+/// Constructors without a `Limits` argument, like [`Png::from_file`](crate::Png::from_file),
+/// only enforce the specification, pass [`Limits::default`] to the `*_with_limits` variants
+/// when the input is untrusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest data length of a single chunk, never above the 2^31 - 1 of the specification
+    pub max_chunk_length: u32,
+    pub max_chunk_count: usize,
+    /// Largest size of a single inflated zlib stream, such as the image data or a text
+    pub max_decompressed_size: usize,
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_chunk_length: 1 << 28,
+            max_chunk_count: 1 << 16,
+            max_decompressed_size: 1 << 28,
+            max_width: 1 << 16,
+            max_height: 1 << 16,
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum LimitError {
+    #[error("Limit exceeded, a chunk has {0} bytes of data but at most {1} are allowed")]
+    ChunkTooLong(u32, u32),
+    #[error("Limit exceeded, the image has more than {0} chunks")]
+    TooManyChunks(usize),
+    #[error("Limit exceeded, inflated data is larger than {0} bytes")]
+    DecompressedTooLarge(usize),
+    #[error("Limit exceeded, the image is {0}x{1} but at most {2}x{3} is allowed")]
+    DimensionsTooLarge(u32, u32, u32, u32),
+}
+
+impl Limits {
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_chunk_length: Chunk::MAX_LENGTH,
+            max_chunk_count: usize::MAX,
+            max_decompressed_size: usize::MAX,
+            max_width: Ihdr::MAX_DIMENSION,
+            max_height: Ihdr::MAX_DIMENSION,
+        }
+    }
+
+    /// Checks the length field of a chunk before its data is read
+    pub fn check_chunk_length(&self, length: u32) -> Result<()> {
+        let max = self.max_chunk_length.min(Chunk::MAX_LENGTH);
+
+        if length > max {
+            bail!(LimitError::ChunkTooLong(length, max));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the number of chunks read so far
+    pub fn check_chunk_count(&self, count: usize) -> Result<()> {
+        if count > self.max_chunk_count {
+            bail!(LimitError::TooManyChunks(self.max_chunk_count));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<()> {
+        if width > self.max_width || height > self.max_height {
+            bail!(LimitError::DimensionsTooLarge(
+                width,
+                height,
+                self.max_width,
+                self.max_height
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks a parsed chunk, rejecting headers of images that are too large
+    pub(crate) fn check_chunk(&self, chunk_type: &ChunkType, data: &[u8]) -> Result<()> {
+        if *chunk_type == ChunkType::IHDR {
+            if let Ok(ihdr) = Ihdr::from_data(data) {
+                self.check_dimensions(ihdr.width(), ihdr.height())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_length() {
+        let limits = Limits {
+            max_chunk_length: 10,
+            ..Limits::default()
+        };
+
+        assert!(limits.check_chunk_length(10).is_ok());
+        assert!(limits.check_chunk_length(11).is_err());
+        assert!(Limits::unlimited()
+            .check_chunk_length(Chunk::MAX_LENGTH + 1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let limits = Limits {
+            max_width: 100,
+            max_height: 50,
+            ..Limits::default()
+        };
+
+        assert!(limits.check_dimensions(100, 50).is_ok());
+        assert!(limits.check_dimensions(101, 1).is_err());
+        assert!(limits.check_dimensions(1, 51).is_err());
+    }
+}
@@ -1,33 +1,134 @@
+mod adam7;
+pub mod apng;
 pub mod chunk;
+pub mod color;
+pub mod encryption;
+pub mod exif;
+pub mod filter;
+pub mod ihdr;
+pub mod limits;
+pub mod payload;
+pub mod pixels;
+pub mod png_ref;
+pub mod reader;
+pub mod recipients;
+pub mod salvage;
+pub mod signature;
+pub mod strip;
+pub mod text;
+pub mod validate;
+pub mod writer;
+mod zlib;
 
 use anyhow::{bail, Error, Result};
-use chunk::{chunk_type::ChunkType, Chunk};
+use chunk::{chunk_ref::ChunkRef, chunk_type::ChunkType, Chunk};
+use ihdr::{Ihdr, IhdrError};
+use limits::Limits;
+use payload::preview_message;
+use png_ref::PngRef;
+use reader::PngReader;
+use signature::MessageSignature;
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
 use std::str::FromStr;
 use thiserror::Error as ThisError;
+use writer::PngWriter;
 
 pub struct Png {
     chunks: Vec<Chunk>,
+    trailing_data: Vec<u8>,
+    limits: Limits,
+}
+
+/// Where a new chunk is placed relative to the critical chunks of the image.
+///
+/// Chunks written after `IEND` are considered trailing garbage by strict decoders,
+/// so the default keeps them inside the image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkPlacement {
+    #[default]
+    BeforeIend,
+    BeforeFirstIdat,
+    AfterIhdr,
+    AfterLastIdat,
 }
 
 impl Png {
     pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
 
-    const fn _from_chunks(chunks: Vec<Chunk>) -> Self {
-        Self { chunks }
+    const fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Self {
+            chunks,
+            trailing_data: Vec::new(),
+            limits: Limits::unlimited(),
+        }
     }
 
     pub fn from_file(path: &Path) -> Result<Self> {
-        let bytes: &[u8] = &std::fs::read(path)?;
+        Self::from_file_with_limits(path, Limits::unlimited())
+    }
+
+    pub fn from_file_with_limits(path: &Path, limits: Limits) -> Result<Self> {
+        let file = File::open(path)?;
+
+        Self::from_reader_with_limits(BufReader::new(file), limits)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        Self::from_reader_with_limits(reader, Limits::unlimited())
+    }
 
-        Self::try_from(bytes)
+    /// Reads an image refusing any input that goes over `limits`,
+    /// which are also used later when inflating its data
+    pub fn from_reader_with_limits<R: Read>(reader: R, limits: Limits) -> Result<Self> {
+        let mut reader = PngReader::with_limits(reader, limits)?;
+        let chunks = reader.by_ref().collect::<Result<_>>()?;
+
+        let mut png = Self::from_chunks(chunks);
+        png.limits = limits;
+        reader.into_inner().read_to_end(&mut png.trailing_data)?;
+
+        Ok(png)
     }
 
     pub fn append_chunk(&mut self, chunk: Chunk) {
         self.chunks.push(chunk);
     }
 
+    /// Inserts a chunk at the position given by `placement`.
+    ///
+    /// If the anchor chunk of the placement is missing the chunk is inserted before `IEND`,
+    /// or appended when there is no `IEND` either.
+    pub fn insert_chunk(&mut self, chunk: Chunk, placement: ChunkPlacement) {
+        let index = self
+            .placement_index(placement)
+            .or_else(|| self.placement_index(ChunkPlacement::BeforeIend))
+            .unwrap_or(self.chunks.len());
+
+        self.chunks.insert(index, chunk);
+    }
+
+    fn placement_index(&self, placement: ChunkPlacement) -> Option<usize> {
+        let position = |chunk_type: &ChunkType| {
+            self.chunks
+                .iter()
+                .position(|chunk| chunk.chunk_type() == chunk_type)
+        };
+
+        match placement {
+            ChunkPlacement::BeforeIend => position(&ChunkType::IEND),
+            ChunkPlacement::BeforeFirstIdat => position(&ChunkType::IDAT),
+            ChunkPlacement::AfterIhdr => position(&ChunkType::IHDR).map(|i| i + 1),
+            ChunkPlacement::AfterLastIdat => self
+                .chunks
+                .iter()
+                .rposition(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+                .map(|i| i + 1),
+        }
+    }
+
     pub fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type)?;
 
@@ -40,11 +141,111 @@ impl Png {
         bail!(PngError::NoChunkTypeFound(chunk_type.to_string()))
     }
 
+    /// Removes the `n`th chunk of the given type, counting from 0
+    pub fn remove_nth_chunk(&mut self, chunk_type: &str, n: usize) -> Result<Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+
+        let Some(index) = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| *chunk.chunk_type() == chunk_type)
+            .nth(n)
+            .map(|(i, _)| i)
+        else {
+            bail!(PngError::NoChunkTypeFound(chunk_type.to_string()));
+        };
+
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Removes every chunk of the given type, returning them in order
+    pub fn remove_all_chunks(&mut self, chunk_type: &str) -> Result<Vec<Chunk>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| *chunk.chunk_type() == chunk_type);
+        self.chunks = kept;
+
+        Ok(removed)
+    }
+
+    /// Keeps only the chunks for which `keep` returns true
+    pub fn retain<F: FnMut(&Chunk) -> bool>(&mut self, keep: F) {
+        self.chunks.retain(keep);
+    }
+
     #[must_use]
-    fn _chunks(&self) -> &[Chunk] {
+    pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
     }
 
+    #[must_use]
+    pub const fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub const fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Bytes stored after the `IEND` chunk, which decoders ignore
+    #[must_use]
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
+
+    pub fn set_trailing_data(&mut self, data: Vec<u8>) {
+        self.trailing_data = data;
+    }
+
+    /// Removes the trailing data from the image and returns it
+    pub fn take_trailing_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing_data)
+    }
+
+    /// Chunks stored after `IEND`, where version 1.0 appended messages.
+    ///
+    /// Empty unless the trailing data is made only of complete chunks with valid crcs.
+    #[must_use]
+    pub fn trailing_chunks(&self) -> Vec<Chunk> {
+        ChunkRef::parse_all(&self.trailing_data, &self.limits)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Chunk::from)
+            .collect()
+    }
+
+    /// Moves the [`trailing_chunks`](Self::trailing_chunks) before `IEND`, returning how many
+    /// were moved. Trailing data that is not made of chunks is left untouched.
+    pub fn adopt_trailing_chunks(&mut self) -> usize {
+        let chunks = self.trailing_chunks();
+        if chunks.is_empty() {
+            return 0;
+        }
+
+        self.trailing_data.clear();
+        for chunk in &chunks {
+            self.insert_chunk(chunk.clone(), ChunkPlacement::BeforeIend);
+        }
+
+        chunks.len()
+    }
+
+    /// Parses and validates the `IHDR` chunk of the image
+    pub fn ihdr(&self) -> Result<Ihdr> {
+        let Some(chunk) = self
+            .chunks
+            .iter()
+            .find(|chunk| *chunk.chunk_type() == ChunkType::IHDR)
+        else {
+            bail!(PngError::InvalidIhdr(IhdrError::Missing));
+        };
+
+        Ihdr::try_from(chunk)
+    }
+
     #[must_use]
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type).ok()?;
@@ -54,6 +255,36 @@ impl Png {
             .find(|&chunk| *chunk.chunk_type() == chunk_type)
     }
 
+    /// Iterates over every chunk of the given type, which is empty for an invalid type
+    pub fn chunks_by_type<'a>(&'a self, chunk_type: &str) -> impl Iterator<Item = &'a Chunk> + 'a {
+        let chunk_type = ChunkType::from_str(chunk_type).ok();
+
+        self.chunks
+            .iter()
+            .filter(move |chunk| chunk_type.as_ref() == Some(chunk.chunk_type()))
+    }
+
+    /// The `n`th chunk of the given type, counting from 0
+    #[must_use]
+    pub fn nth_chunk_by_type(&self, chunk_type: &str, n: usize) -> Option<&Chunk> {
+        self.chunks_by_type(chunk_type).nth(n)
+    }
+
+    /// Writes the whole image to `writer` chunk by chunk
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = PngWriter::new(writer)?;
+
+        for chunk in &self.chunks {
+            writer.write_chunk(chunk)?;
+        }
+
+        let mut writer = writer.finish()?;
+        writer.write_all(&self.trailing_data)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
     #[must_use]
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut vec: Vec<u8> = Self::STANDARD_HEADER.to_vec();
@@ -63,6 +294,8 @@ impl Png {
             vec.extend(chunk.as_bytes());
         }
 
+        vec.extend(&self.trailing_data);
+
         vec
     }
 }
@@ -71,39 +304,62 @@ impl TryFrom<&[u8]> for Png {
     type Error = Error;
 
     fn try_from(value: &[u8]) -> Result<Self> {
-        if value.len() < Self::STANDARD_HEADER.len() {
-            bail!(PngError::NoHeaderProvided);
-        }
-
-        let (header, mut chunks) = value.split_at(Self::STANDARD_HEADER.len());
-        let header: [u8; Self::STANDARD_HEADER.len()] = header.try_into()?;
-
-        if header != Self::STANDARD_HEADER {
-            bail!(PngError::InvalidHeader(header));
-        }
-
-        let mut png = Self { chunks: Vec::new() };
-
-        while !chunks.is_empty() {
-            let chunk = Chunk::try_from(chunks)?;
-
-            (_, chunks) = chunks.split_at(chunk.length() as usize + Chunk::METEDATA_LENGTH);
-
-            png.chunks.push(chunk);
-        }
-
-        Ok(png)
+        PngRef::try_from(value).map(Self::from)
     }
 }
 
 impl Display for Png {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for chunk in &self.chunks {
-            writeln!(f, "[{}]", chunk.chunk_type())?;
+            let description = describe_chunk(chunk.chunk_type(), chunk.data(), &self.limits);
+            writeln!(f, "[{}]{description}", chunk.chunk_type())?;
         }
 
-        Ok(())
+        describe_trailing_data(f, &self.trailing_data, &self.limits)
+    }
+}
+
+/// Lists the chunks stored after `IEND` by version 1.0, or gives the size of the trailing data
+fn describe_trailing_data(
+    f: &mut std::fmt::Formatter<'_>,
+    data: &[u8],
+    limits: &Limits,
+) -> std::fmt::Result {
+    match ChunkRef::parse_all(data, limits) {
+        Ok(chunks) => {
+            for chunk in chunks {
+                let description = describe_chunk(chunk.chunk_type(), chunk.data(), limits);
+                writeln!(f, "[{}]{description} after IEND", chunk.chunk_type())?;
+            }
+        }
+        Err(_) => writeln!(f, "{} bytes of trailing data", data.len())?,
     }
+
+    Ok(())
+}
+
+/// What is shown after the type of a chunk when listing an image, with a leading space,
+/// or nothing for chunks without a description
+fn describe_chunk(chunk_type: &ChunkType, data: &[u8], limits: &Limits) -> String {
+    let description = match *chunk_type {
+        ChunkType::IHDR => Ihdr::from_data(data).ok().map(|ihdr| ihdr.to_string()),
+        ChunkType::EXIF => exif::Exif::from_data(data)
+            .ok()
+            .map(|exif| format!("{} tags", exif.entries().len())),
+        ChunkType::SGNT => MessageSignature::from_data(data).ok().map(|signature| {
+            format!(
+                "signature of {} by {}",
+                signature.code(),
+                signature.key().fingerprint()
+            )
+        }),
+        _ if !chunk_type.is_public() => Some(preview_message(data, limits)),
+        _ => None,
+    };
+
+    description
+        .map(|description| format!(" {description}"))
+        .unwrap_or_default()
 }
 
 #[derive(Debug, ThisError)]
@@ -114,6 +370,10 @@ pub enum PngError {
     InvalidHeader([u8; 8]),
     #[error("No message with code {0} found encoded in image")]
     NoChunkTypeFound(String),
+    #[error("Png parsing error, invalid IHDR chunk")]
+    InvalidIhdr(#[source] IhdrError),
+    #[error("No text with keyword {0} found in image")]
+    NoTextKeywordFound(String),
 }
 
 #[cfg(test)]
@@ -131,7 +391,7 @@ mod tests {
 
     fn testing_png() -> Png {
         let chunks = testing_chunks();
-        Png::_from_chunks(chunks)
+        Png::from_chunks(chunks)
     }
 
     fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
@@ -144,9 +404,9 @@ mod tests {
     #[test]
     fn test_from_chunks() {
         let chunks = testing_chunks();
-        let png = Png::_from_chunks(chunks);
+        let png = Png::from_chunks(chunks);
 
-        assert_eq!(png._chunks().len(), 3);
+        assert_eq!(png.chunks().len(), 3);
     }
 
     #[test]
@@ -210,7 +470,7 @@ mod tests {
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
-        let chunks = png._chunks();
+        let chunks = png.chunks();
         assert_eq!(chunks.len(), 3);
     }
 
@@ -240,6 +500,155 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am the second").unwrap());
+
+        let data: Vec<String> = png
+            .chunks_by_type("miDl")
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+
+        assert_eq!(data, ["I am another chunk", "I am the second"]);
+        assert_eq!(
+            png.nth_chunk_by_type("miDl", 1).unwrap().data(),
+            b"I am the second"
+        );
+        assert!(png.nth_chunk_by_type("miDl", 2).is_none());
+        assert_eq!(png.chunks_by_type("bad!").count(), 0);
+    }
+
+    #[test]
+    fn test_remove_nth_and_all_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am the second").unwrap());
+        png.append_chunk(chunk_from_strings("miDl", "I am the third").unwrap());
+
+        let removed = png.remove_nth_chunk("miDl", 1).unwrap();
+        assert_eq!(removed.data(), b"I am the second");
+        assert!(png.remove_nth_chunk("miDl", 2).is_err());
+
+        let removed = png.remove_all_chunks("miDl").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.remove_all_chunks("miDl").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut png = testing_png();
+        png.retain(|chunk| chunk.chunk_type().is_critical());
+
+        assert_eq!(png.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let appended = chunk_from_strings("ruSt", "after the end").unwrap();
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(appended.as_bytes());
+
+        let mut png = Png::from_reader(bytes.as_slice()).unwrap();
+
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_data(), appended.as_bytes());
+
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, bytes);
+
+        assert_eq!(png.take_trailing_data(), appended.as_bytes());
+        assert_eq!(png.as_bytes(), PNG_FILE);
+
+        png.set_trailing_data(b"payload".to_vec());
+        assert!(png.as_bytes().ends_with(b"IEND\xAEB`\x82payload"));
+    }
+
+    #[test]
+    fn test_insert_chunk_before_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.insert_chunk(
+            chunk_from_strings("TeSt", "Message").unwrap(),
+            ChunkPlacement::default(),
+        );
+
+        let bytes = png.as_bytes();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+
+        assert_eq!(types.last().unwrap(), "IEND");
+        assert_eq!(types[types.len() - 2], "TeSt");
+    }
+
+    #[test]
+    fn test_insert_chunk_placements() {
+        let position = |placement| {
+            let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+            png.insert_chunk(chunk_from_strings("TeSt", "Message").unwrap(), placement);
+            let chunks = png.chunks();
+            let index = chunks
+                .iter()
+                .position(|chunk| chunk.chunk_type().to_string() == "TeSt")
+                .unwrap();
+            (
+                chunks[index - 1].chunk_type().to_string(),
+                chunks[index + 1].chunk_type().to_string(),
+            )
+        };
+
+        assert_eq!(position(ChunkPlacement::AfterIhdr).0, "IHDR");
+        assert_eq!(position(ChunkPlacement::BeforeFirstIdat).1, "IDAT");
+        assert_eq!(position(ChunkPlacement::AfterLastIdat).0, "IDAT");
+        assert_eq!(position(ChunkPlacement::BeforeIend).1, "IEND");
+    }
+
+    #[test]
+    fn test_insert_chunk_without_anchor() {
+        let mut png = testing_png();
+        png.insert_chunk(
+            chunk_from_strings("TeSt", "Message").unwrap(),
+            ChunkPlacement::AfterIhdr,
+        );
+        assert_eq!(&png.chunks()[3].chunk_type().to_string(), "TeSt");
+    }
+
+    #[test]
+    fn test_adopt_trailing_chunks() {
+        // NOTE: version 1.0 appended messages after IEND
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(
+            chunk_from_strings("ruSt", "hello legacy")
+                .unwrap()
+                .as_bytes(),
+        );
+
+        let mut png = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_chunks()[0].data(), b"hello legacy");
+        assert!(png
+            .to_string()
+            .contains("[ruSt] \"hello legacy\" after IEND"));
+        assert!(PngRef::try_from(bytes.as_ref())
+            .unwrap()
+            .to_string()
+            .contains("after IEND"));
+
+        assert_eq!(png.adopt_trailing_chunks(), 1);
+        assert!(png.trailing_data().is_empty());
+        assert_eq!(png.chunks()[7].chunk_type(), &ChunkType::IEND);
+        assert_eq!(png.chunks()[6].data(), b"hello legacy");
+
+        png.set_trailing_data(b"not a chunk".to_vec());
+        assert!(png.trailing_chunks().is_empty());
+        assert_eq!(png.adopt_trailing_chunks(), 0);
+        assert_eq!(png.trailing_data(), b"not a chunk");
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -254,6 +663,14 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_write_to() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut bytes = Vec::new();
+        png.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, png.as_bytes());
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -273,7 +690,7 @@ mod tests {
     }
 
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
-    const PNG_FILE: [u8; 4803] = [
+    pub const PNG_FILE: [u8; 4803] = [
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 50, 0, 0, 0, 50, 8,
         6, 0, 0, 0, 30, 63, 136, 177, 0, 0, 0, 1, 115, 82, 71, 66, 0, 174, 206, 28, 233, 0, 0, 0,
         4, 103, 65, 77, 65, 0, 0, 177, 143, 11, 252, 97, 5, 0, 0, 0, 9, 112, 72, 89, 115, 0, 0, 14,
@@ -518,4 +935,27 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
+
+    // A 10x7 8-bit RGB image interlaced with Adam7, the pixel at (x, y) is [25x, 36y, 10(x + y)]
+    pub const INTERLACED_RGB_FILE: [u8; 236] = [
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 10, 0, 0, 0, 7, 8,
+        2, 0, 0, 1, 201, 201, 123, 162, 0, 0, 0, 179, 73, 68, 65, 84, 120, 218, 13, 205, 33, 18,
+        195, 32, 16, 5, 208, 175, 171, 99, 48, 81, 43, 208, 171, 209, 12, 51, 24, 52, 122, 37, 131,
+        142, 193, 68, 87, 172, 226, 26, 24, 14, 193, 17, 176, 220, 164, 149, 79, 61, 0, 88, 72, 16,
+        88, 64, 173, 104, 90, 250, 128, 113, 117, 56, 176, 186, 174, 2, 132, 139, 131, 149, 224,
+        122, 72, 43, 252, 189, 29, 239, 36, 91, 250, 126, 214, 254, 194, 224, 19, 113, 55, 240,
+        128, 63, 200, 48, 225, 142, 129, 91, 240, 35, 228, 19, 42, 140, 114, 84, 223, 52, 15, 173,
+        71, 95, 152, 237, 227, 206, 109, 215, 177, 223, 179, 59, 64, 31, 67, 23, 211, 29, 201, 10,
+        113, 35, 215, 201, 15, 74, 139, 242, 161, 127, 87, 110, 83, 44, 23, 142, 197, 73, 241, 173,
+        164, 94, 242, 40, 178, 74, 61, 229, 1, 38, 155, 233, 120, 250, 56, 147, 204, 220, 166, 244,
+        89, 199, 124, 214, 124, 207, 252, 254, 0, 234, 185, 80, 206, 162, 84, 172, 133, 0, 0, 0, 0,
+        73, 69, 78, 68, 174, 66, 96, 130,
+    ];
+    // A 9x9 1-bit grayscale checkerboard interlaced with Adam7, starting with a black pixel
+    pub const INTERLACED_GRAY_FILE: [u8; 75] = [
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 9, 0, 0, 0, 9, 1, 0,
+        0, 0, 1, 191, 237, 11, 43, 0, 0, 0, 18, 73, 68, 65, 84, 120, 218, 99, 96, 192, 2, 62, 192,
+        225, 170, 6, 56, 2, 0, 108, 30, 9, 89, 96, 11, 171, 185, 0, 0, 0, 0, 73, 69, 78, 68, 174,
+        66, 96, 130,
+    ];
 }
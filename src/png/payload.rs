@@ -0,0 +1,610 @@
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+use thiserror::Error as ThisError;
+
+use super::encryption::is_encrypted;
+use super::limits::{LimitError, Limits};
+use super::recipients::is_encrypted_to_recipients;
+use super::zlib;
+
+/// Marks the start of a payload with a header, data without it is a plain UTF-8 message
+pub const MAGIC: [u8; 4] = *b"PNGp";
+pub const VERSION: u8 = 1;
+
+pub const TEXT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Number of characters or bytes shown by [`Payload::preview`]
+const PREVIEW_LENGTH: usize = 32;
+/// Bytes inflated by [`preview_message`], enough for one more character than a preview shows
+const PREVIEW_BYTES: usize = (PREVIEW_LENGTH + 1) * 4;
+
+/// Content types guessed from the extension of embedded files
+const CONTENT_TYPES: [(&str, &str); 14] = [
+    ("txt", TEXT_CONTENT_TYPE),
+    ("md", "text/markdown"),
+    ("html", "text/html"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+/// Data embedded as a message, with the content type and the name of the file it came from.
+///
+/// Plain text messages are stored as is, other payloads start with a small header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    content_type: String,
+    filename: Option<String>,
+    compression: Compression,
+    data: Vec<u8>,
+}
+
+/// Compression of the data of a payload, flagged in its header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Deflate,
+    Zstd,
+}
+
+impl Compression {
+    const fn flags(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Deflate => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    fn from_flags(flags: u8) -> Result<Self> {
+        match flags {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Deflate),
+            2 => Ok(Self::Zstd),
+            _ => bail!(PayloadError::UnknownCompression(flags)),
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data.to_vec()),
+            Self::Deflate => zlib::compress(data, flate2::Compression::best()),
+            Self::Zstd => Ok(zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?),
+        }
+    }
+
+    /// Inflates `data`, failing once the output grows past `limit` bytes
+    fn decompress(self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        let decompressed = self.decompress_start(data, limit.saturating_add(1))?;
+
+        if decompressed.len() > limit {
+            bail!(LimitError::DecompressedTooLarge(limit));
+        }
+
+        Ok(decompressed)
+    }
+
+    /// Inflates at most the first `length` bytes of `data`
+    fn decompress_start(self, data: &[u8], length: usize) -> Result<Vec<u8>> {
+        let reader: Box<dyn Read> = match self {
+            Self::None => Box::new(data),
+            Self::Deflate => Box::new(ZlibDecoder::new(data)),
+            Self::Zstd => Box::new(zstd::Decoder::new(data)?),
+        };
+
+        let mut decompressed = Vec::new();
+        reader
+            .take(u64::try_from(length).unwrap_or(u64::MAX))
+            .read_to_end(&mut decompressed)?;
+
+        Ok(decompressed)
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Deflate => write!(f, "deflate"),
+            Self::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum PayloadError {
+    #[error("Error parsing payload, version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Error parsing payload, the header is truncated")]
+    Truncated,
+    #[error("Invalid payload {0}, it is {1} bytes long but at most 255 are allowed")]
+    FieldTooLong(&'static str, usize),
+    #[error("Error parsing payload, the {0} is not valid UTF-8")]
+    InvalidField(&'static str),
+    #[error("Error parsing payload, unknown compression {0}")]
+    UnknownCompression(u8),
+}
+
+impl Payload {
+    pub fn new(data: Vec<u8>, content_type: String, filename: Option<String>) -> Result<Self> {
+        if content_type.len() > usize::from(u8::MAX) {
+            bail!(PayloadError::FieldTooLong(
+                "content type",
+                content_type.len()
+            ))
+        }
+        if let Some(filename) = &filename {
+            if filename.len() > usize::from(u8::MAX) {
+                bail!(PayloadError::FieldTooLong("filename", filename.len()))
+            }
+        }
+
+        Ok(Self {
+            content_type,
+            filename,
+            compression: Compression::None,
+            data,
+        })
+    }
+
+    #[must_use]
+    pub fn text(text: String) -> Self {
+        Self {
+            content_type: TEXT_CONTENT_TYPE.to_string(),
+            filename: None,
+            compression: Compression::None,
+            data: text.into_bytes(),
+        }
+    }
+
+    #[must_use]
+    pub fn binary(data: Vec<u8>) -> Self {
+        Self {
+            content_type: BINARY_CONTENT_TYPE.to_string(),
+            filename: None,
+            compression: Compression::None,
+            data,
+        }
+    }
+
+    /// Reads a file to embed, guessing its content type from the extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+
+        let content_type = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| {
+                CONTENT_TYPES
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            })
+            .map_or(BINARY_CONTENT_TYPE, |(_, content_type)| content_type);
+        let filename = path
+            .file_name()
+            .map(|filename| filename.to_string_lossy().into_owned());
+
+        Self::new(data, content_type.to_string(), filename)
+    }
+
+    /// Parses the data of a message, data without a header is taken as a plain message
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::parse(data, &Limits::unlimited())
+    }
+
+    /// Parses the data of a message, inflating compressed data up to the decompression limit
+    pub fn parse(data: &[u8], limits: &Limits) -> Result<Self> {
+        Self::parse_with(data, |compression, data| {
+            compression.decompress(data, limits.max_decompressed_size)
+        })
+    }
+
+    /// Parses the header of a message, leaving its data to `inflate`
+    fn parse_with(
+        data: &[u8],
+        inflate: impl FnOnce(Compression, &[u8]) -> Result<Vec<u8>>,
+    ) -> Result<Self> {
+        let Some(data) = data.strip_prefix(&MAGIC) else {
+            let content_type = match std::str::from_utf8(data) {
+                Ok(_) => TEXT_CONTENT_TYPE,
+                Err(_) => BINARY_CONTENT_TYPE,
+            };
+
+            return Ok(Self {
+                content_type: content_type.to_string(),
+                filename: None,
+                compression: Compression::None,
+                data: data.to_vec(),
+            });
+        };
+
+        let mut fields = Fields { data };
+
+        let version = fields.byte()?;
+        if version != VERSION {
+            bail!(PayloadError::UnsupportedVersion(version))
+        }
+        let compression = Compression::from_flags(fields.byte()?)?;
+
+        let content_type = fields.string("content type")?;
+        let filename = fields.string("filename")?;
+
+        Ok(Self {
+            content_type,
+            filename: (!filename.is_empty()).then_some(filename),
+            compression,
+            data: inflate(compression, fields.data)?,
+        })
+    }
+
+    /// Bytes to embed, a header followed by the data compressed if it gets smaller.
+    ///
+    /// Plain text without a filename or compression is stored as is, so older versions can
+    /// still read it.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let plain = self.content_type == TEXT_CONTENT_TYPE
+            && self.filename.is_none()
+            && self.compression == Compression::None
+            && !has_reserved_magic(&self.data);
+        if plain {
+            return Ok(self.data.clone());
+        }
+
+        // NOTE: data that does not shrink is stored uncompressed
+        let compressed = match self.compression {
+            Compression::None => None,
+            compression => Some((compression, compression.compress(&self.data)?))
+                .filter(|(_, compressed)| compressed.len() < self.data.len()),
+        };
+        let (compression, data) = compressed.as_ref().map_or(
+            (Compression::None, self.data.as_slice()),
+            |(compression, data)| (*compression, data.as_slice()),
+        );
+
+        let filename = self.filename.as_deref().unwrap_or_default();
+
+        let mut bytes = Vec::with_capacity(
+            MAGIC.len() + 4 + self.content_type.len() + filename.len() + data.len(),
+        );
+        bytes.extend(MAGIC);
+        bytes.extend([VERSION, compression.flags()]);
+        for field in [self.content_type.as_str(), filename] {
+            // NOTE: lengths are checked when the payload is created
+            bytes.push(u8::try_from(field.len()).unwrap_or(u8::MAX));
+            bytes.extend(field.as_bytes());
+        }
+        bytes.extend(data);
+
+        Ok(bytes)
+    }
+
+    #[must_use]
+    pub const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub const fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    #[must_use]
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[must_use]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The data as text, if it is a text payload in valid UTF-8
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        if !self.content_type.starts_with("text/") {
+            return None;
+        }
+
+        std::str::from_utf8(&self.data).ok()
+    }
+
+    /// Short description of the payload, the start of the text or a hex dump of the first bytes
+    #[must_use]
+    pub fn preview(&self) -> String {
+        self.describe(&format!("{} bytes", self.data.len()), false)
+    }
+
+    /// Preview of the payload giving its size as `size`, `cut` when the data is only the start
+    fn describe(&self, size: &str, cut: bool) -> String {
+        if let Some(text) = self.as_text() {
+            let mut preview: String = text.chars().take(PREVIEW_LENGTH).collect();
+            if cut || preview.len() < text.len() {
+                preview.push_str("...");
+            }
+
+            return format!("{preview:?}");
+        }
+
+        let mut preview = self.content_type.clone();
+        if let Some(filename) = &self.filename {
+            let _ = write!(preview, " {filename:?}");
+        }
+        let _ = write!(preview, ", {size}:");
+
+        for byte in self.data.iter().take(PREVIEW_LENGTH / 2) {
+            let _ = write!(preview, " {byte:02x}");
+        }
+        if cut || self.data.len() > PREVIEW_LENGTH / 2 {
+            preview.push_str(" ...");
+        }
+
+        preview
+    }
+}
+
+/// Length prefixed fields of a payload header
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl Fields<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let Some((&byte, rest)) = self.data.split_first() else {
+            bail!(PayloadError::Truncated)
+        };
+        self.data = rest;
+
+        Ok(byte)
+    }
+
+    fn string(&mut self, name: &'static str) -> Result<String> {
+        let length = usize::from(self.byte()?);
+        if self.data.len() < length {
+            bail!(PayloadError::Truncated)
+        }
+
+        let (field, rest) = self.data.split_at(length);
+        self.data = rest;
+
+        match String::from_utf8(field.to_vec()) {
+            Ok(field) => Ok(field),
+            Err(_) => bail!(PayloadError::InvalidField(name)),
+        }
+    }
+}
+
+/// Whether `data` starts like a payload header or an encrypted message, plain text starting
+/// this way gets a header so it is not mistaken for one
+fn has_reserved_magic(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC) || is_encrypted(data) || is_encrypted_to_recipients(data)
+}
+
+/// Preview of the message stored in a chunk, without decrypting it.
+///
+/// Only the start of compressed data is inflated, never more than the decompression limit.
+#[must_use]
+pub fn preview_message(data: &[u8], limits: &Limits) -> String {
+    if is_encrypted(data) || is_encrypted_to_recipients(data) {
+        return format!("encrypted, {} bytes", data.len());
+    }
+
+    let length = limits.max_decompressed_size.min(PREVIEW_BYTES);
+    let mut compressed = 0;
+    let payload = Payload::parse_with(data, |compression, data| {
+        compressed = data.len();
+        match compression {
+            Compression::None => Ok(data.to_vec()),
+            compression => compression.decompress_start(data, length),
+        }
+    });
+
+    match payload {
+        Ok(mut payload)
+            if payload.compression != Compression::None && payload.data.len() == length =>
+        {
+            // NOTE: the inflated start can end in the middle of a character
+            if let Err(error) = std::str::from_utf8(&payload.data) {
+                if error.error_len().is_none() {
+                    payload.data.truncate(error.valid_up_to());
+                }
+            }
+
+            let size = format!("{compressed} bytes compressed with {}", payload.compression);
+            payload.describe(&size, true)
+        }
+        Ok(payload) => payload.preview(),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_round_trip() {
+        let payload = Payload::new(
+            vec![0, 159, 146, 150],
+            "image/png".to_string(),
+            Some("photo.png".to_string()),
+        )
+        .unwrap();
+
+        let bytes = payload.to_bytes().unwrap();
+        assert!(bytes.starts_with(&MAGIC));
+        assert_eq!(Payload::from_bytes(&bytes).unwrap(), payload);
+
+        let payload = Payload::text("hey".to_string());
+        assert_eq!(payload.to_bytes().unwrap(), b"hey");
+        assert_eq!(
+            Payload::from_bytes(&payload.to_bytes().unwrap()).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_compression() {
+        let text = "This is where your secret message will be!".repeat(10);
+
+        for compression in [Compression::Deflate, Compression::Zstd] {
+            let mut payload = Payload::text(text.clone());
+            payload.set_compression(compression);
+
+            let bytes = payload.to_bytes().unwrap();
+            assert!(bytes.len() < text.len());
+            assert_eq!(Payload::from_bytes(&bytes).unwrap(), payload);
+
+            let limits = Limits {
+                max_decompressed_size: text.len() - 1,
+                ..Limits::default()
+            };
+            let error = Payload::parse(&bytes, &limits).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref(),
+                Some(LimitError::DecompressedTooLarge(_))
+            ));
+        }
+
+        // NOTE: data that does not shrink is stored uncompressed
+        let mut payload = Payload::text("hey".to_string());
+        payload.set_compression(Compression::Zstd);
+
+        let parsed = Payload::from_bytes(&payload.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.compression(), Compression::None);
+        assert_eq!(parsed.data(), b"hey");
+    }
+
+    #[test]
+    fn test_text_with_reserved_magic() {
+        for text in ["PNGe hello", "PNGrs rocks", "PNGp"] {
+            let payload = Payload::text(text.to_string());
+
+            let bytes = payload.to_bytes().unwrap();
+            assert!(bytes.starts_with(&MAGIC));
+            assert!(!is_encrypted(&bytes) && !is_encrypted_to_recipients(&bytes));
+            assert_eq!(Payload::from_bytes(&bytes).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_plain_message() {
+        let payload = Payload::from_bytes(b"hey").unwrap();
+        assert_eq!(payload.as_text(), Some("hey"));
+        assert_eq!(payload.filename(), None);
+
+        let payload = Payload::from_bytes(&[0, 159, 146, 150]).unwrap();
+        assert_eq!(payload.content_type(), BINARY_CONTENT_TYPE);
+        assert_eq!(payload.as_text(), None);
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview_message(b"hey", &Limits::default()), "\"hey\"");
+        assert_eq!(
+            preview_message(&"a".repeat(40).into_bytes(), &Limits::default()),
+            format!("\"{}...\"", "a".repeat(32))
+        );
+
+        let payload = Payload::new(
+            vec![0, 255],
+            "image/png".to_string(),
+            Some("a.png".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            preview_message(&payload.to_bytes().unwrap(), &Limits::default()),
+            "image/png \"a.png\", 2 bytes: 00 ff"
+        );
+
+        let payload = Payload::binary(vec![0; 1000]);
+        assert!(
+            preview_message(&payload.to_bytes().unwrap(), &Limits::default())
+                .starts_with("application/octet-stream, 1000 bytes: 00")
+        );
+    }
+
+    #[test]
+    fn test_preview_compressed() {
+        let mut payload = Payload::text("é".repeat(1000));
+        payload.set_compression(Compression::Deflate);
+        assert_eq!(
+            preview_message(&payload.to_bytes().unwrap(), &Limits::default()),
+            format!("\"{}...\"", "é".repeat(32))
+        );
+
+        // NOTE: only the start is inflated, so data above the limit can still be previewed
+        let mut payload = Payload::binary(vec![0; 1 << 20]);
+        payload.set_compression(Compression::Zstd);
+        let bytes = payload.to_bytes().unwrap();
+        let limits = Limits {
+            max_decompressed_size: 1000,
+            ..Limits::default()
+        };
+        assert!(Payload::parse(&bytes, &limits).is_err());
+        assert_eq!(
+            preview_message(&bytes, &limits),
+            format!(
+                "{BINARY_CONTENT_TYPE}, {} bytes compressed with zstd: {}",
+                bytes.len() - MAGIC.len() - 4 - BINARY_CONTENT_TYPE.len(),
+                "00 ".repeat(16) + "..."
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        let mut bytes = Payload::binary(b"hey".to_vec()).to_bytes().unwrap();
+
+        let error = Payload::from_bytes(&bytes[..8]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PayloadError::Truncated)
+        ));
+
+        bytes[4] = 2;
+        let error = Payload::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PayloadError::UnsupportedVersion(2))
+        ));
+
+        bytes[4] = VERSION;
+        bytes[5] = 3;
+        let error = Payload::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PayloadError::UnknownCompression(3))
+        ));
+
+        let error = Payload::new(Vec::new(), "a".repeat(256), None).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PayloadError::FieldTooLong("content type", 256))
+        ));
+    }
+}
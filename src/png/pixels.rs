@@ -0,0 +1,567 @@
+use anyhow::{bail, Result};
+use flate2::Compression;
+use thiserror::Error as ThisError;
+
+use super::adam7;
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::filter::{self, FilterStrategy};
+use super::ihdr::{ColorType, Ihdr, InterlaceMethod};
+use super::limits::LimitError;
+use super::{zlib, Png};
+
+/// Decoded image samples, laid out row by row without filter type bytes.
+///
+/// Samples keep the PNG layout: sub-byte depths are packed with the leftmost pixel
+/// in the high bits, 16-bit samples are big endian and indexed images hold palette indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelBuffer {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: ColorType,
+    data: Vec<u8>,
+}
+
+/// Options used when building a PNG from a [`PixelBuffer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    pub filter: FilterStrategy,
+    /// zlib compression level, from 0 (none) to 9 (best)
+    pub compression_level: u32,
+    /// RGB palette entries, required for indexed images
+    pub palette: Option<Vec<[u8; 3]>>,
+    pub interlace: InterlaceMethod,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            filter: FilterStrategy::default(),
+            compression_level: Compression::default().level(),
+            palette: None,
+            interlace: InterlaceMethod::None,
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum PixelError {
+    #[error("Error decoding pixels, the image has no IDAT chunks")]
+    MissingImageData,
+    #[error("Error decoding pixels, expected {0} bytes of image data but found {1}")]
+    InvalidDataLength(usize, usize),
+    #[error("Error encoding pixels, indexed images need a palette")]
+    MissingPalette,
+    #[error("Error encoding pixels, a palette must have between 1 and {1} entries but it has {0}")]
+    InvalidPaletteLength(usize, usize),
+    #[error("Error encoding pixels, {0:?} images can not have a palette")]
+    UnexpectedPalette(ColorType),
+    #[error("Error encoding pixels, compression level {0} must be between 0 and 9")]
+    InvalidCompressionLevel(u32),
+}
+
+impl PixelBuffer {
+    /// Wraps raw samples, checking that the format is valid and `data` has exactly one image
+    pub fn new(
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        bit_depth: u8,
+        data: Vec<u8>,
+    ) -> Result<Self> {
+        let ihdr = Ihdr::new(width, height, bit_depth, color_type, InterlaceMethod::None)?;
+
+        let expected = ihdr.stride(width) * height as usize;
+        if data.len() != expected {
+            bail!(PixelError::InvalidDataLength(expected, data.len()));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            data,
+        })
+    }
+
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[must_use]
+    pub const fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    #[must_use]
+    pub const fn color_type(&self) -> ColorType {
+        self.color_type
+    }
+
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[must_use]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    #[must_use]
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Bytes used by each row of the buffer
+    #[must_use]
+    pub const fn stride(&self) -> usize {
+        (self.width as usize * self.color_type.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    #[must_use]
+    pub fn row(&self, y: u32) -> Option<&[u8]> {
+        self.data.chunks_exact(self.stride()).nth(y as usize)
+    }
+}
+
+impl Png {
+    /// Maximum amount of compressed data stored in a single `IDAT` chunk
+    pub const IDAT_CHUNK_SIZE: usize = 1 << 16;
+
+    /// Builds a complete PNG from raw samples using the default [`EncodeOptions`]
+    pub fn encode_pixels(
+        width: u32,
+        height: u32,
+        color_type: ColorType,
+        bit_depth: u8,
+        data: &[u8],
+    ) -> Result<Self> {
+        let pixels = PixelBuffer::new(width, height, color_type, bit_depth, data.to_vec())?;
+
+        Self::from_pixels(&pixels, &EncodeOptions::default())
+    }
+
+    /// Builds a complete PNG with `IHDR`, `PLTE` when needed, `IDAT` and `IEND` chunks
+    pub fn from_pixels(pixels: &PixelBuffer, options: &EncodeOptions) -> Result<Self> {
+        if options.compression_level > 9 {
+            bail!(PixelError::InvalidCompressionLevel(
+                options.compression_level
+            ));
+        }
+
+        let ihdr = Ihdr::new(
+            pixels.width,
+            pixels.height,
+            pixels.bit_depth,
+            pixels.color_type,
+            options.interlace,
+        )?;
+
+        let mut chunks = vec![ihdr.to_chunk()];
+
+        match (&options.palette, pixels.color_type) {
+            (None, ColorType::Indexed) => bail!(PixelError::MissingPalette),
+            (Some(_), ColorType::Grayscale | ColorType::GrayscaleAlpha) => {
+                bail!(PixelError::UnexpectedPalette(pixels.color_type))
+            }
+            (Some(palette), color_type) => {
+                let max = if color_type == ColorType::Indexed {
+                    1 << pixels.bit_depth
+                } else {
+                    256
+                };
+                if !(1..=max).contains(&palette.len()) {
+                    bail!(PixelError::InvalidPaletteLength(palette.len(), max));
+                }
+                chunks.push(Chunk::new(ChunkType::PLTE, palette.concat()));
+            }
+            (None, _) => {}
+        }
+
+        let filtered = match options.interlace {
+            InterlaceMethod::None => filter::filter(
+                &pixels.data,
+                pixels.stride(),
+                bytes_per_pixel(&ihdr),
+                options.filter,
+            ),
+            InterlaceMethod::Adam7 => {
+                adam7::interlace(&pixels.data, &ihdr, bytes_per_pixel(&ihdr), options.filter)
+            }
+        };
+        let compressed = zlib::compress(&filtered, Compression::new(options.compression_level))?;
+
+        chunks.extend(
+            compressed
+                .chunks(Self::IDAT_CHUNK_SIZE)
+                .map(|data| Chunk::new(ChunkType::IDAT, data.to_vec())),
+        );
+        chunks.push(Chunk::new(ChunkType::IEND, Vec::new()));
+
+        Ok(Self::from_chunks(chunks))
+    }
+
+    /// Concatenated data of every `IDAT` chunk, the compressed image stream
+    #[must_use]
+    pub fn image_data(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+            .flat_map(|chunk| chunk.data().iter().copied())
+            .collect()
+    }
+
+    /// Inflates the image data and reverses the scanline filters into raw samples
+    pub fn decode_pixels(&self) -> Result<PixelBuffer> {
+        let ihdr = self.ihdr()?;
+
+        if !self
+            .chunks
+            .iter()
+            .any(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+        {
+            bail!(PixelError::MissingImageData);
+        }
+
+        self.limits.check_dimensions(ihdr.width(), ihdr.height())?;
+        let size = image_size(&ihdr, self.limits.max_decompressed_size)?;
+
+        let data = zlib::decompress(&self.image_data(), self.limits.max_decompressed_size)?;
+
+        let data = match ihdr.interlace_method() {
+            InterlaceMethod::None => {
+                let stride = ihdr.stride(ihdr.width());
+                let expected = size + ihdr.height() as usize;
+                if data.len() < expected {
+                    bail!(PixelError::InvalidDataLength(expected, data.len()));
+                }
+
+                filter::unfilter(&data[..expected], stride, bytes_per_pixel(&ihdr))?
+            }
+            InterlaceMethod::Adam7 => adam7::deinterlace(
+                &data,
+                &ihdr,
+                bytes_per_pixel(&ihdr),
+                self.limits.max_decompressed_size,
+            )?,
+        };
+
+        Ok(PixelBuffer {
+            width: ihdr.width(),
+            height: ihdr.height(),
+            bit_depth: ihdr.bit_depth(),
+            color_type: ihdr.color_type(),
+            data,
+        })
+    }
+}
+
+/// Size in bytes of the unfiltered pixels of the image, refusing sizes above `limit`
+pub(super) fn image_size(ihdr: &Ihdr, limit: usize) -> Result<usize> {
+    let size = ihdr
+        .stride(ihdr.width())
+        .checked_mul(ihdr.height() as usize);
+
+    match size {
+        Some(size) if size <= limit => Ok(size),
+        _ => bail!(LimitError::DecompressedTooLarge(limit)),
+    }
+}
+
+/// Distance in bytes to the corresponding byte of the previous pixel used by the filters
+const fn bytes_per_pixel(ihdr: &Ihdr) -> usize {
+    let bytes = ihdr.bits_per_pixel() / 8;
+
+    if bytes == 0 {
+        1
+    } else {
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::filter::FilterType;
+    use crate::png::limits::{LimitError, Limits};
+    use crate::png::tests::{INTERLACED_GRAY_FILE, INTERLACED_RGB_FILE, PNG_FILE};
+
+    fn png_from_scanlines(ihdr: &Ihdr, scanlines: &[u8]) -> Png {
+        let idat = zlib::compress(scanlines, Compression::default()).unwrap();
+        let (first, second) = idat.split_at(idat.len() / 2);
+
+        Png::from_chunks(vec![
+            ihdr.to_chunk(),
+            Chunk::new(ChunkType::IDAT, first.to_vec()),
+            Chunk::new(ChunkType::IDAT, second.to_vec()),
+            Chunk::new(ChunkType::IEND, Vec::new()),
+        ])
+    }
+
+    #[test]
+    fn test_decode_pixels_from_image_file() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+
+        assert_eq!(pixels.data().len(), 50 * 50 * 4);
+        assert_eq!(pixels.stride(), 200);
+        assert_eq!(&pixels.row(25).unwrap()[100..104], &[240, 240, 240, 255]);
+        assert_eq!(
+            crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(pixels.data()),
+            3_827_446_951
+        );
+    }
+
+    #[test]
+    fn test_decode_pixels_limits() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.set_limits(Limits {
+            max_decompressed_size: 1000,
+            ..Limits::default()
+        });
+
+        let error = png.decode_pixels().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::DecompressedTooLarge(1000))
+        ));
+
+        png.set_limits(Limits {
+            max_height: 10,
+            ..Limits::default()
+        });
+        assert!(png.decode_pixels().is_err());
+    }
+
+    #[test]
+    fn test_decode_pixels_huge_header() {
+        for interlace_method in [InterlaceMethod::None, InterlaceMethod::Adam7] {
+            let ihdr = Ihdr::new(65535, 65535, 16, ColorType::Rgba, interlace_method).unwrap();
+
+            let mut png = png_from_scanlines(&ihdr, &[0; 16]);
+            png.set_limits(Limits::default());
+
+            let error = png.decode_pixels().unwrap_err();
+            assert!(matches!(
+                error.downcast_ref(),
+                Some(LimitError::DecompressedTooLarge(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_pixels_sub_byte_depth() {
+        let ihdr = Ihdr::new(10, 2, 1, ColorType::Grayscale, InterlaceMethod::None).unwrap();
+        #[rustfmt::skip]
+        let scanlines = [
+            0, 0b1010_1010, 0b1100_0000,
+            2, 0b0101_0101, 0b0000_0000,
+        ];
+
+        let pixels = png_from_scanlines(&ihdr, &scanlines)
+            .decode_pixels()
+            .unwrap();
+
+        assert_eq!(
+            pixels.data(),
+            &[0b1010_1010, 0b1100_0000, 0b1111_1111, 0b1100_0000]
+        );
+    }
+
+    #[test]
+    fn test_decode_pixels_sixteen_bit() {
+        let ihdr = Ihdr::new(2, 1, 16, ColorType::GrayscaleAlpha, InterlaceMethod::None).unwrap();
+        let scanlines = [1, 1, 2, 3, 4, 1, 1, 1, 1];
+
+        let pixels = png_from_scanlines(&ihdr, &scanlines)
+            .decode_pixels()
+            .unwrap();
+
+        assert_eq!(pixels.data(), &[1, 2, 3, 4, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_encode_pixels_round_trip() {
+        let formats = [
+            (ColorType::Grayscale, 1),
+            (ColorType::Grayscale, 4),
+            (ColorType::Grayscale, 16),
+            (ColorType::Rgb, 8),
+            (ColorType::GrayscaleAlpha, 16),
+            (ColorType::Rgba, 8),
+        ];
+
+        for (color_type, bit_depth) in formats {
+            let ihdr = Ihdr::new(7, 5, bit_depth, color_type, InterlaceMethod::None).unwrap();
+            let data: Vec<u8> = (0..=255u8)
+                .cycle()
+                .step_by(13)
+                .take(ihdr.stride(7) * 5)
+                .collect();
+
+            let png = Png::encode_pixels(7, 5, color_type, bit_depth, &data).unwrap();
+            let bytes = png.as_bytes();
+            let pixels = Png::try_from(bytes.as_ref())
+                .unwrap()
+                .decode_pixels()
+                .unwrap();
+
+            assert_eq!(png.ihdr().unwrap(), ihdr);
+            assert_eq!(pixels.data(), data);
+        }
+    }
+
+    #[test]
+    fn test_encode_pixels_chunk_layout() {
+        let pixels = PixelBuffer::new(2, 2, ColorType::Indexed, 2, vec![0b0001_0000, 0b1011_0000]);
+        let options = EncodeOptions {
+            filter: FilterStrategy::Fixed(FilterType::Paeth),
+            compression_level: 9,
+            palette: Some(vec![[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255]]),
+            ..EncodeOptions::default()
+        };
+
+        let png = Png::from_pixels(&pixels.unwrap(), &options).unwrap();
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+
+        assert_eq!(types, ["IHDR", "PLTE", "IDAT", "IEND"]);
+        assert_eq!(png.chunks()[1].data().len(), 12);
+        assert_eq!(
+            png.decode_pixels().unwrap().data(),
+            &[0b0001_0000, 0b1011_0000]
+        );
+    }
+
+    #[test]
+    fn test_encode_pixels_errors() {
+        let pixels = PixelBuffer::new(1, 1, ColorType::Indexed, 8, vec![0]).unwrap();
+        assert!(Png::from_pixels(&pixels, &EncodeOptions::default()).is_err());
+
+        let options = EncodeOptions {
+            compression_level: 10,
+            ..EncodeOptions::default()
+        };
+        let pixels = PixelBuffer::new(1, 1, ColorType::Grayscale, 8, vec![0]).unwrap();
+        assert!(Png::from_pixels(&pixels, &options).is_err());
+
+        assert!(PixelBuffer::new(2, 2, ColorType::Rgb, 8, vec![0; 11]).is_err());
+
+        let options = EncodeOptions {
+            palette: Some(vec![[0, 0, 0]]),
+            ..EncodeOptions::default()
+        };
+        let pixels = PixelBuffer::new(1, 1, ColorType::GrayscaleAlpha, 8, vec![0, 0]).unwrap();
+        let error = Png::from_pixels(&pixels, &options).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PixelError::UnexpectedPalette(ColorType::GrayscaleAlpha))
+        ));
+
+        let options = EncodeOptions {
+            palette: Some(vec![[0, 0, 0]; 5]),
+            ..EncodeOptions::default()
+        };
+        let pixels = PixelBuffer::new(1, 1, ColorType::Indexed, 2, vec![0]).unwrap();
+        let error = Png::from_pixels(&pixels, &options).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(PixelError::InvalidPaletteLength(5, 4))
+        ));
+    }
+
+    #[test]
+    fn test_reencode_image_file() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut pixels = png.decode_pixels().unwrap();
+        pixels.data_mut()[0] = 42;
+
+        let reencoded = Png::from_pixels(&pixels, &EncodeOptions::default()).unwrap();
+
+        assert_eq!(reencoded.decode_pixels().unwrap(), pixels);
+    }
+
+    #[test]
+    fn test_decode_interlaced_rgb_file() {
+        let png = Png::try_from(&INTERLACED_RGB_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+
+        let expected: Vec<u8> = (0..7u8)
+            .flat_map(|y| (0..10u8).flat_map(move |x| [25 * x, 36 * y, 10 * (x + y)]))
+            .collect();
+
+        assert_eq!(
+            png.ihdr().unwrap().interlace_method(),
+            InterlaceMethod::Adam7
+        );
+        assert_eq!(pixels.data(), expected);
+    }
+
+    #[test]
+    fn test_decode_interlaced_gray_file() {
+        let png = Png::try_from(&INTERLACED_GRAY_FILE[..]).unwrap();
+        let pixels = png.decode_pixels().unwrap();
+
+        for y in 0..9 {
+            let row = pixels.row(y).unwrap();
+            let expected = if y % 2 == 0 {
+                [0b0101_0101, 0b0000_0000]
+            } else {
+                [0b1010_1010, 0b1000_0000]
+            };
+            assert_eq!(row, expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_interlaced_round_trip() {
+        for file in [
+            &INTERLACED_RGB_FILE[..],
+            &INTERLACED_GRAY_FILE[..],
+            &PNG_FILE[..],
+        ] {
+            let pixels = Png::try_from(file).unwrap().decode_pixels().unwrap();
+            let options = EncodeOptions {
+                interlace: InterlaceMethod::Adam7,
+                ..EncodeOptions::default()
+            };
+
+            let png = Png::from_pixels(&pixels, &options).unwrap();
+            let bytes = png.as_bytes();
+            let png = Png::try_from(bytes.as_ref()).unwrap();
+
+            assert_eq!(
+                png.ihdr().unwrap().interlace_method(),
+                InterlaceMethod::Adam7
+            );
+            assert_eq!(png.decode_pixels().unwrap(), pixels);
+        }
+    }
+
+    #[test]
+    fn test_decode_pixels_truncated_data() {
+        let ihdr = Ihdr::new(4, 4, 8, ColorType::Rgb, InterlaceMethod::None).unwrap();
+        let png = png_from_scanlines(&ihdr, &[0; 13 * 3]);
+
+        assert!(png.decode_pixels().is_err());
+    }
+
+    #[test]
+    fn test_decode_pixels_without_image_data() {
+        let ihdr = Ihdr::new(4, 4, 8, ColorType::Rgb, InterlaceMethod::None).unwrap();
+        let png = Png::from_chunks(vec![ihdr.to_chunk()]);
+
+        assert!(png.decode_pixels().is_err());
+    }
+}
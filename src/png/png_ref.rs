@@ -0,0 +1,183 @@
+use anyhow::{bail, Error, Result};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::chunk::{chunk_ref::ChunkRef, chunk_type::ChunkType, Chunk};
+use super::limits::Limits;
+use super::{describe_chunk, describe_trailing_data, Png, PngError};
+
+/// A PNG whose chunks borrow their data from the parsed buffer, which can be a memory map.
+///
+/// Listing or searching chunks does not copy any chunk data,
+/// convert it into a [`Png`] with `Png::from` when chunks need to be modified.
+pub struct PngRef<'a> {
+    chunks: Vec<ChunkRef<'a>>,
+    trailing_data: &'a [u8],
+    limits: Limits,
+}
+
+impl<'a> PngRef<'a> {
+    #[must_use]
+    pub fn chunks(&self) -> &[ChunkRef<'a>] {
+        &self.chunks
+    }
+
+    /// Bytes found after the `IEND` chunk, which are not part of the image
+    #[must_use]
+    pub const fn trailing_data(&self) -> &'a [u8] {
+        self.trailing_data
+    }
+
+    #[must_use]
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&ChunkRef<'a>> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+
+        self.chunks
+            .iter()
+            .find(|&chunk| *chunk.chunk_type() == chunk_type)
+    }
+}
+
+impl<'a> PngRef<'a> {
+    /// Parses `value` refusing any input that goes over `limits`
+    pub fn parse(value: &'a [u8], limits: Limits) -> Result<Self> {
+        if value.len() < Png::STANDARD_HEADER.len() {
+            bail!(PngError::NoHeaderProvided);
+        }
+
+        let (header, mut chunks) = value.split_at(Png::STANDARD_HEADER.len());
+        let header: [u8; Png::STANDARD_HEADER.len()] = header.try_into()?;
+
+        if header != Png::STANDARD_HEADER {
+            bail!(PngError::InvalidHeader(header));
+        }
+
+        let mut png = Self {
+            chunks: Vec::new(),
+            trailing_data: &[],
+            limits,
+        };
+
+        while !chunks.is_empty() {
+            limits.check_chunk_count(png.chunks.len() + 1)?;
+
+            let chunk = ChunkRef::parse(chunks, &limits)?;
+            limits.check_chunk(chunk.chunk_type(), chunk.data())?;
+
+            (_, chunks) = chunks.split_at(chunk.total_length());
+
+            let end = *chunk.chunk_type() == ChunkType::IEND;
+            png.chunks.push(chunk);
+
+            // NOTE: IEND is the logical end of the image, anything after it is kept as is
+            if end {
+                png.trailing_data = chunks;
+                break;
+            }
+        }
+
+        Ok(png)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PngRef<'a> {
+    type Error = Error;
+
+    fn try_from(value: &'a [u8]) -> Result<Self> {
+        Self::parse(value, Limits::unlimited())
+    }
+}
+
+impl From<PngRef<'_>> for Png {
+    fn from(value: PngRef<'_>) -> Self {
+        let mut png = Self::from_chunks(value.chunks.into_iter().map(Chunk::from).collect());
+        png.set_trailing_data(value.trailing_data.to_vec());
+        png.set_limits(value.limits);
+
+        png
+    }
+}
+
+impl Display for PngRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in &self.chunks {
+            let description = describe_chunk(chunk.chunk_type(), chunk.data(), &self.limits);
+            writeln!(f, "[{}]{description}", chunk.chunk_type())?;
+        }
+
+        describe_trailing_data(f, self.trailing_data, &self.limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::limits::LimitError;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_png_ref_from_bytes() {
+        let png = PngRef::try_from(&PNG_FILE[..]).unwrap();
+        let idat = png.chunk_by_type("IDAT").unwrap();
+
+        assert_eq!(png.chunks().len(), 7);
+        assert!(PNG_FILE.as_ptr_range().contains(&idat.data().as_ptr()));
+    }
+
+    #[test]
+    fn test_png_ref_to_png() {
+        let png = Png::from(PngRef::try_from(&PNG_FILE[..]).unwrap());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_png_ref_invalid_header() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[1] = 0;
+        assert!(PngRef::try_from(bytes.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_png_ref_limits() {
+        let limits = |limits: Limits| PngRef::parse(&PNG_FILE, limits).err().unwrap();
+
+        let error = limits(Limits {
+            max_chunk_count: 6,
+            ..Limits::default()
+        });
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::TooManyChunks(6))
+        ));
+
+        let error = limits(Limits {
+            max_chunk_length: 1000,
+            ..Limits::default()
+        });
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::ChunkTooLong(_, 1000))
+        ));
+
+        let error = limits(Limits {
+            max_width: 49,
+            ..Limits::default()
+        });
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::DimensionsTooLarge(50, 50, 49, _))
+        ));
+    }
+
+    #[test]
+    fn test_png_ref_trailing_data() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(b"not a chunk");
+
+        let png = PngRef::try_from(bytes.as_ref()).unwrap();
+
+        assert_eq!(png.chunks().len(), 7);
+        assert_eq!(png.trailing_data(), b"not a chunk");
+        assert_eq!(Png::from(png).as_bytes(), bytes);
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{bail, Result};
+use std::io::{ErrorKind, Read};
+use std::str::FromStr;
+
+use super::chunk::{chunk_type::ChunkType, Chunk, ChunkError};
+use super::limits::Limits;
+use super::{Png, PngError};
+
+/// Reads a PNG one chunk at a time from any [`Read`] source.
+///
+/// The signature is checked on creation and the crc of every chunk is checked as it is read,
+/// so callers can stop at any point without reading the rest of the input.
+/// Reading stops after `IEND`, leaving any trailing data in the inner reader.
+pub struct PngReader<R: Read> {
+    reader: R,
+    limits: Limits,
+    count: usize,
+    finished: bool,
+    ended: bool,
+}
+
+impl<R: Read> PngReader<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_limits(reader, Limits::unlimited())
+    }
+
+    /// Creates a reader that fails as soon as the input goes over `limits`
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<Self> {
+        let mut header = [0; Png::STANDARD_HEADER.len()];
+
+        if read_full(&mut reader, &mut header)? < header.len() {
+            bail!(PngError::NoHeaderProvided);
+        }
+
+        if header != Png::STANDARD_HEADER {
+            bail!(PngError::InvalidHeader(header));
+        }
+
+        Ok(Self {
+            reader,
+            limits,
+            count: 0,
+            finished: false,
+            ended: false,
+        })
+    }
+
+    /// Reads the next chunk, returning `None` once `IEND` was read or the input is exhausted
+    pub fn read_chunk(&mut self) -> Result<Option<Chunk>> {
+        if self.ended {
+            return Ok(None);
+        }
+
+        let mut length = [0; Chunk::LENGTH_LENGTH];
+        match read_full(&mut self.reader, &mut length)? {
+            0 => return Ok(None),
+            Chunk::LENGTH_LENGTH => {}
+            _ => bail!(ChunkError::NoDataLengthProvided),
+        }
+        let length = u32::from_be_bytes(length);
+
+        self.count += 1;
+        self.limits.check_chunk_count(self.count)?;
+        self.limits.check_chunk_length(length)?;
+
+        let mut chunk_code = [0; Chunk::TYPE_LENGTH];
+        if read_full(&mut self.reader, &mut chunk_code)? < Chunk::TYPE_LENGTH {
+            bail!(ChunkError::NoChunkTypeProvided);
+        }
+        let chunk_type = ChunkType::try_from(chunk_code)?;
+
+        // NOTE: take is used instead of allocating the whole length up front,
+        // so a bogus length can not allocate more than what the input actually has
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(u64::from(length))
+            .read_to_end(&mut data)?;
+        if data.len() < length as usize {
+            bail!(ChunkError::NonMatchingDataLength(
+                length as usize,
+                data.len()
+            ));
+        }
+
+        let mut crc = [0; Chunk::CRC_LENGTH];
+        if read_full(&mut self.reader, &mut crc)? < Chunk::CRC_LENGTH {
+            bail!(ChunkError::NoCrcProvided);
+        }
+        let crc = u32::from_be_bytes(crc);
+
+        self.limits.check_chunk(&chunk_type, &data)?;
+        self.ended = chunk_type == ChunkType::IEND;
+
+        Chunk::from_parts(chunk_type, data, crc).map(Some)
+    }
+
+    /// Reads chunks until one of the given type is found, leaving the rest of the input unread
+    pub fn chunk_by_type(&mut self, chunk_type: &str) -> Result<Option<Chunk>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+
+        for chunk in self.by_ref() {
+            let chunk = chunk?;
+            if *chunk.chunk_type() == chunk_type {
+                return Ok(Some(chunk));
+            }
+        }
+
+        Ok(None)
+    }
+
+    #[must_use]
+    pub const fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for PngReader<R> {
+    type Item = Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let chunk = self.read_chunk().transpose();
+        if !matches!(chunk, Some(Ok(_))) {
+            self.finished = true;
+        }
+
+        chunk
+    }
+}
+
+/// Like `read_exact` but returns how many bytes were read instead of failing on end of input
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_reader_reads_all_chunks() {
+        let reader = PngReader::new(Cursor::new(&PNG_FILE[..])).unwrap();
+        let chunks: Vec<Chunk> = reader.collect::<Result<_>>().unwrap();
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        assert_eq!(chunks.len(), png.chunks().len());
+        assert_eq!(chunks.last().unwrap().chunk_type(), &ChunkType::IEND);
+    }
+
+    #[test]
+    fn test_reader_stops_early() {
+        let mut reader = PngReader::new(Cursor::new(&PNG_FILE[..])).unwrap();
+        let chunk = reader.chunk_by_type("IHDR").unwrap().unwrap();
+        assert_eq!(chunk.chunk_type(), &ChunkType::IHDR);
+
+        let position = reader.into_inner().position();
+        assert!(position < PNG_FILE.len() as u64);
+    }
+
+    #[test]
+    fn test_reader_stops_at_iend() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(b"trailing");
+
+        let mut reader = PngReader::new(Cursor::new(bytes)).unwrap();
+        let chunks: Vec<Chunk> = reader.by_ref().collect::<Result<_>>().unwrap();
+
+        let mut trailing = Vec::new();
+        reader.into_inner().read_to_end(&mut trailing).unwrap();
+
+        assert_eq!(chunks.len(), 7);
+        assert_eq!(trailing, b"trailing");
+    }
+
+    #[test]
+    fn test_reader_limits() {
+        let limits = Limits {
+            max_chunk_count: 3,
+            ..Limits::default()
+        };
+        let reader = PngReader::with_limits(Cursor::new(&PNG_FILE[..]), limits).unwrap();
+        let result: Result<Vec<Chunk>> = reader.collect();
+
+        assert!(result.is_err());
+
+        let mut bytes = PNG_FILE[..Png::STANDARD_HEADER.len()].to_vec();
+        bytes.extend(Chunk::MAX_LENGTH.to_be_bytes());
+        bytes.extend(b"IDAT");
+        let mut reader = PngReader::new(Cursor::new(bytes)).unwrap();
+
+        assert!(reader.read_chunk().is_err());
+    }
+
+    #[test]
+    fn test_reader_invalid_header() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[0] = 13;
+        assert!(PngReader::new(Cursor::new(bytes)).is_err());
+        assert!(PngReader::new(Cursor::new(&PNG_FILE[..4])).is_err());
+    }
+
+    #[test]
+    fn test_reader_invalid_crc() {
+        let mut bytes = PNG_FILE.to_vec();
+        // NOTE: last byte of the IHDR crc
+        bytes[32] ^= 0xff;
+        let mut reader = PngReader::new(Cursor::new(bytes)).unwrap();
+
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        let bytes = &PNG_FILE[..PNG_FILE.len() - 2];
+        let reader = PngReader::new(Cursor::new(bytes)).unwrap();
+        let result: Result<Vec<Chunk>> = reader.collect();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{OsRng, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use thiserror::Error as ThisError;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+use super::encryption::{open, seal, EncryptionError};
+
+/// Marks the start of a message encrypted to recipients, so decoders can tell it apart
+pub const MAGIC: [u8; 4] = *b"PNGr";
+pub const VERSION: u8 = 1;
+
+const RECIPIENT_PREFIX: &str = "pngrs-public-";
+const IDENTITY_PREFIX: &str = "pngrs-secret-";
+const WRAP_INFO: &[u8] = b"pngrs x25519 file key";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+const STANZA_LENGTH: usize = KEY_LENGTH + KEY_LENGTH + TAG_LENGTH;
+
+/// X25519 public key a message can be encrypted to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    key: PublicKey,
+}
+
+/// X25519 secret key able to decrypt the messages encrypted to its [`Recipient`]
+#[derive(Clone)]
+pub struct Identity {
+    secret: StaticSecret,
+}
+
+#[derive(Debug, ThisError)]
+pub enum RecipientError {
+    #[error("Invalid key, expected {0} followed by 64 hexadecimal digits")]
+    InvalidKey(&'static str),
+    #[error("Error encrypting message, at least one recipient is needed")]
+    NoRecipients,
+    #[error("Error decrypting message, it is not encrypted to recipients")]
+    NotEncrypted,
+    #[error("Error decrypting message, version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Error decrypting message, the message is truncated")]
+    Truncated,
+    #[error("Error decrypting message, it is not encrypted to any of the given identities")]
+    NoMatchingIdentity,
+}
+
+impl Recipient {
+    #[must_use]
+    pub fn bytes(&self) -> &[u8; KEY_LENGTH] {
+        self.key.as_bytes()
+    }
+}
+
+impl From<[u8; KEY_LENGTH]> for Recipient {
+    fn from(value: [u8; KEY_LENGTH]) -> Self {
+        Self {
+            key: PublicKey::from(value),
+        }
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self::from(parse_key(s, RECIPIENT_PREFIX)?))
+    }
+}
+
+impl Display for Recipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{RECIPIENT_PREFIX}{}", hex::encode(self.key.as_bytes()))
+    }
+}
+
+impl Identity {
+    #[must_use]
+    pub fn generate() -> Self {
+        Self {
+            secret: StaticSecret::random_from_rng(OsRng),
+        }
+    }
+
+    #[must_use]
+    pub fn recipient(&self) -> Recipient {
+        Recipient {
+            key: PublicKey::from(&self.secret),
+        }
+    }
+
+    /// Key that decrypts the stanza if it was made for this identity
+    fn unwrap_file_key(&self, stanza: &[u8]) -> Option<[u8; KEY_LENGTH]> {
+        let (ephemeral, wrapped) = stanza.split_at(KEY_LENGTH);
+        let ephemeral = PublicKey::from(<[u8; KEY_LENGTH]>::try_from(ephemeral).ok()?);
+
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let wrap_key = wrap_key(&shared, &ephemeral, &self.recipient().key).ok()?;
+
+        let payload = Payload {
+            msg: wrapped,
+            aad: &[],
+        };
+        let file_key = open::<ChaCha20Poly1305>(&wrap_key, &[0; NONCE_LENGTH], payload).ok()?;
+
+        file_key.try_into().ok()
+    }
+}
+
+impl FromStr for Identity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self {
+            secret: StaticSecret::from(parse_key(s, IDENTITY_PREFIX)?),
+        })
+    }
+}
+
+impl Display for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{IDENTITY_PREFIX}{}",
+            hex::encode(self.secret.as_bytes())
+        )
+    }
+}
+
+pub(super) fn parse_key(s: &str, prefix: &'static str) -> Result<[u8; KEY_LENGTH]> {
+    let key = s
+        .trim()
+        .strip_prefix(prefix)
+        .and_then(|key| hex::decode(key).ok())
+        .and_then(|key| key.try_into().ok());
+
+    match key {
+        Some(key) => Ok(key),
+        None => bail!(RecipientError::InvalidKey(prefix)),
+    }
+}
+
+/// Key used to wrap the file key for one recipient, derived from the secret shared between a
+/// fresh ephemeral key and the recipient key
+fn wrap_key(
+    shared: &SharedSecret,
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Result<[u8; KEY_LENGTH]> {
+    // NOTE: low order points give a predictable secret, they are never produced by honest keys
+    if !shared.was_contributory() {
+        bail!(EncryptionError::DecryptionFailed)
+    }
+
+    let salt = [ephemeral.as_bytes().as_slice(), recipient.as_bytes()].concat();
+
+    let mut key = [0; KEY_LENGTH];
+    if Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(WRAP_INFO, &mut key)
+        .is_err()
+    {
+        bail!("Error deriving key, invalid length")
+    }
+
+    Ok(key)
+}
+
+/// Whether `data` starts like a message produced by [`encrypt_to_recipients`]
+#[must_use]
+pub fn is_encrypted_to_recipients(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Encrypts `plaintext` so any of the `recipients` can decrypt it with their [`Identity`].
+///
+/// A random file key encrypts the message, and it is wrapped once per recipient with a key agreed
+/// from a fresh ephemeral X25519 key, in the style of age. The header with every wrapped key is
+/// authenticated along with the message.
+pub fn encrypt_to_recipients(plaintext: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    let Ok(count) = u16::try_from(recipients.len()) else {
+        bail!("Error encrypting message, too many recipients")
+    };
+    if count == 0 {
+        bail!(RecipientError::NoRecipients)
+    }
+
+    let mut file_key = [0; KEY_LENGTH];
+    OsRng.fill_bytes(&mut file_key);
+
+    let mut data = Vec::new();
+    data.extend(MAGIC);
+    data.push(VERSION);
+    data.extend(count.to_be_bytes());
+
+    for recipient in recipients {
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let ephemeral = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&recipient.key);
+        let wrap_key = wrap_key(&shared, &ephemeral, &recipient.key)?;
+
+        let payload = Payload {
+            msg: &file_key,
+            aad: &[],
+        };
+        let wrapped = seal::<ChaCha20Poly1305>(&wrap_key, &[0; NONCE_LENGTH], payload)?;
+
+        data.extend(ephemeral.as_bytes());
+        data.extend(wrapped);
+    }
+
+    let mut nonce = [0; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+    data.extend(nonce);
+
+    let payload = Payload {
+        msg: plaintext,
+        aad: &data,
+    };
+    let ciphertext = seal::<ChaCha20Poly1305>(&file_key, &nonce, payload)?;
+
+    data.extend(ciphertext);
+
+    Ok(data)
+}
+
+/// Decrypts a message produced by [`encrypt_to_recipients`] with the first identity it was
+/// encrypted to, failing if any byte of the message was changed
+pub fn decrypt_with_identities(data: &[u8], identities: &[Identity]) -> Result<Vec<u8>> {
+    if !is_encrypted_to_recipients(data) {
+        bail!(RecipientError::NotEncrypted)
+    }
+
+    let Some(&[version, a, b]) = data.get(MAGIC.len()..MAGIC.len() + 3) else {
+        bail!(RecipientError::Truncated)
+    };
+    if version != VERSION {
+        bail!(RecipientError::UnsupportedVersion(version))
+    }
+
+    let stanzas_start = MAGIC.len() + 3;
+    let header_length = stanzas_start + usize::from(u16::from_be_bytes([a, b])) * STANZA_LENGTH;
+
+    let Some(stanzas) = data.get(stanzas_start..header_length) else {
+        bail!(RecipientError::Truncated)
+    };
+    let Some(nonce) = data.get(header_length..header_length + NONCE_LENGTH) else {
+        bail!(RecipientError::Truncated)
+    };
+    // NOTE: the nonce is authenticated along with the rest of the header
+    let (aad, ciphertext) = data.split_at(header_length + NONCE_LENGTH);
+    if ciphertext.len() < TAG_LENGTH {
+        bail!(RecipientError::Truncated)
+    }
+
+    let file_key = stanzas.chunks_exact(STANZA_LENGTH).find_map(|stanza| {
+        identities
+            .iter()
+            .find_map(|identity| identity.unwrap_file_key(stanza))
+    });
+    let Some(file_key) = file_key else {
+        bail!(RecipientError::NoMatchingIdentity)
+    };
+
+    let payload = Payload {
+        msg: ciphertext,
+        aad,
+    };
+
+    open::<ChaCha20Poly1305>(&file_key, nonce, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_round_trip() {
+        let identity = Identity::generate();
+        let recipient = identity.recipient();
+
+        let parsed = Recipient::from_str(&recipient.to_string()).unwrap();
+        assert_eq!(parsed, recipient);
+
+        let parsed = Identity::from_str(&identity.to_string()).unwrap();
+        assert_eq!(parsed.recipient(), recipient);
+
+        let error = Recipient::from_str(&identity.to_string()).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::InvalidKey(RECIPIENT_PREFIX))
+        ));
+        assert!(Recipient::from_str("pngrs-public-00").is_err());
+    }
+
+    #[test]
+    fn test_multiple_recipients() {
+        let alice = Identity::generate();
+        let bob = Identity::generate();
+        let eve = Identity::generate();
+
+        let data = encrypt_to_recipients(b"hey", &[alice.recipient(), bob.recipient()]).unwrap();
+
+        assert!(is_encrypted_to_recipients(&data));
+        assert_eq!(decrypt_with_identities(&data, &[alice]).unwrap(), b"hey");
+        assert_eq!(decrypt_with_identities(&data, &[eve, bob]).unwrap(), b"hey");
+
+        let error = decrypt_with_identities(&data, &[Identity::generate()]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::NoMatchingIdentity)
+        ));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let identity = Identity::generate();
+        let data = encrypt_to_recipients(b"hey", &[identity.recipient()]).unwrap();
+        let header_length = MAGIC.len() + 3 + STANZA_LENGTH;
+
+        // NOTE: the nonce is part of the authenticated header
+        for i in [header_length, data.len() - 1] {
+            let mut tampered = data.clone();
+            tampered[i] ^= 1;
+
+            let error =
+                decrypt_with_identities(&tampered, std::slice::from_ref(&identity)).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref(),
+                Some(EncryptionError::DecryptionFailed)
+            ));
+        }
+
+        let mut tampered = data;
+        tampered[header_length - 1] ^= 1;
+        let error = decrypt_with_identities(&tampered, &[identity]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::NoMatchingIdentity)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_envelope() {
+        let identity = Identity::generate();
+
+        let error = encrypt_to_recipients(b"hey", &[]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::NoRecipients)
+        ));
+
+        let error = decrypt_with_identities(b"hey", &[]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::NotEncrypted)
+        ));
+
+        let data = encrypt_to_recipients(b"hey", &[identity.recipient()]).unwrap();
+        let error = decrypt_with_identities(&data[..40], &[identity]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(RecipientError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+use anyhow::Result;
+use std::fmt::Display;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::limits::Limits;
+use super::Png;
+
+/// Result of a lenient parse, every chunk that could be recovered and what went wrong
+pub struct Salvage {
+    chunks: Vec<SalvagedChunk>,
+    diagnostics: Vec<Diagnostic>,
+    limits: Limits,
+}
+
+/// A recovered chunk with where it was found and whether it was damaged.
+///
+/// The crc of the chunk is recalculated, so writing it back repairs the file.
+pub struct SalvagedChunk {
+    chunk: Chunk,
+    offset: usize,
+    status: ChunkStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStatus {
+    Valid,
+    CrcMismatch,
+    /// The input ended before the chunk did, only the available data was kept
+    Truncated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    InvalidHeader,
+    CrcMismatch {
+        offset: usize,
+        chunk_type: ChunkType,
+        stored: u32,
+        actual: u32,
+    },
+    Garbage {
+        offset: usize,
+        length: usize,
+    },
+    Truncated {
+        offset: usize,
+        chunk_type: ChunkType,
+        missing: usize,
+    },
+}
+
+impl Salvage {
+    #[must_use]
+    pub fn chunks(&self) -> &[SalvagedChunk] {
+        &self.chunks
+    }
+
+    #[must_use]
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Whether the input was parsed without finding any problem
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    #[must_use]
+    pub fn into_png(self) -> Png {
+        let mut png = Png::from_chunks(self.chunks.into_iter().map(|chunk| chunk.chunk).collect());
+        png.set_limits(self.limits);
+
+        png
+    }
+}
+
+impl SalvagedChunk {
+    #[must_use]
+    pub const fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// Offset in bytes of the chunk from the start of the input
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[must_use]
+    pub const fn status(&self) -> ChunkStatus {
+        self.status
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "the PNG signature is missing or damaged"),
+            Self::CrcMismatch {
+                offset,
+                chunk_type,
+                stored,
+                actual,
+            } => write!(
+                f,
+                "{chunk_type} chunk at offset {offset} has crc {stored} but its data gives {actual}"
+            ),
+            Self::Garbage { offset, length } => {
+                write!(f, "skipped {length} unreadable bytes at offset {offset}")
+            }
+            Self::Truncated {
+                offset,
+                chunk_type,
+                missing,
+            } => write!(
+                f,
+                "{chunk_type} chunk at offset {offset} is truncated, {missing} bytes are missing"
+            ),
+        }
+    }
+}
+
+impl Png {
+    /// Parses as much of `bytes` as possible instead of failing on the first error.
+    ///
+    /// Chunks with a wrong crc are kept and marked, unreadable bytes are skipped until
+    /// the next plausible chunk boundary and a truncated last chunk keeps its available data.
+    pub fn salvage(bytes: &[u8]) -> Result<Salvage> {
+        Self::salvage_with_limits(bytes, Limits::unlimited())
+    }
+
+    /// Salvages `bytes` like [`Png::salvage`], failing only when a recovered chunk
+    /// goes over `limits`
+    pub fn salvage_with_limits(bytes: &[u8], limits: Limits) -> Result<Salvage> {
+        let mut salvage = Salvage {
+            chunks: Vec::new(),
+            diagnostics: Vec::new(),
+            limits,
+        };
+
+        let mut offset = if bytes.starts_with(&Self::STANDARD_HEADER) {
+            Self::STANDARD_HEADER.len()
+        } else {
+            salvage.diagnostics.push(Diagnostic::InvalidHeader);
+            0
+        };
+
+        while offset < bytes.len() {
+            if let Some(candidate) = chunk_at(bytes, offset, false) {
+                salvage.push(&candidate, offset)?;
+                offset = candidate.next;
+                continue;
+            }
+
+            let resync = (offset + 1..bytes.len()).find(|&i| chunk_at(bytes, i, true).is_some());
+
+            match (resync, complete_at(bytes, offset), header_at(bytes, offset)) {
+                (Some(next), _, _) => {
+                    salvage.diagnostics.push(Diagnostic::Garbage {
+                        offset,
+                        length: next - offset,
+                    });
+                    offset = next;
+                }
+                // NOTE: a whole chunk with a wrong crc followed by a few unreadable bytes
+                (None, Some(candidate), _) => {
+                    salvage.push(&candidate, offset)?;
+                    offset = candidate.next;
+                }
+                (None, None, Some((length, chunk_type))) => {
+                    let start = offset + Chunk::LENGTH_LENGTH + Chunk::TYPE_LENGTH;
+                    let available = bytes.len() - start;
+                    let length = length as usize;
+
+                    salvage.check(length)?;
+                    salvage.diagnostics.push(Diagnostic::Truncated {
+                        offset,
+                        chunk_type: chunk_type.clone(),
+                        missing: (length + Chunk::CRC_LENGTH).saturating_sub(available),
+                    });
+                    salvage.chunks.push(SalvagedChunk {
+                        chunk: Chunk::new(
+                            chunk_type,
+                            bytes[start..start + length.min(available)].to_vec(),
+                        ),
+                        offset,
+                        status: ChunkStatus::Truncated,
+                    });
+                    break;
+                }
+                (None, None, None) => {
+                    salvage.diagnostics.push(Diagnostic::Garbage {
+                        offset,
+                        length: bytes.len() - offset,
+                    });
+                    break;
+                }
+            }
+        }
+
+        Ok(salvage)
+    }
+}
+
+impl Salvage {
+    /// Checks the length of a recovered chunk and the number of chunks against the limits
+    fn check(&self, length: usize) -> Result<()> {
+        self.limits.check_chunk_count(self.chunks.len() + 1)?;
+        self.limits.check_chunk_length(length.try_into()?)
+    }
+
+    fn push(&mut self, candidate: &Candidate, offset: usize) -> Result<()> {
+        self.check(candidate.data.len())?;
+
+        let chunk = Chunk::new(candidate.chunk_type.clone(), candidate.data.to_vec());
+        let status = if candidate.is_valid() {
+            ChunkStatus::Valid
+        } else {
+            self.diagnostics.push(Diagnostic::CrcMismatch {
+                offset,
+                chunk_type: chunk.chunk_type().clone(),
+                stored: candidate.stored,
+                actual: chunk.crc(),
+            });
+            ChunkStatus::CrcMismatch
+        };
+
+        self.chunks.push(SalvagedChunk {
+            chunk,
+            offset,
+            status,
+        });
+
+        Ok(())
+    }
+}
+
+/// A chunk found in the input, borrowed until it is recovered
+struct Candidate<'a> {
+    chunk_type: ChunkType,
+    data: &'a [u8],
+    stored: u32,
+    /// Offset right after the crc of the chunk
+    next: usize,
+}
+
+impl Candidate<'_> {
+    fn is_valid(&self) -> bool {
+        Chunk::calculate_crc(&self.chunk_type, self.data) == self.stored
+    }
+}
+
+/// Length and type at `offset` if they look like the start of a chunk
+fn header_at(bytes: &[u8], offset: usize) -> Option<(u32, ChunkType)> {
+    let header = bytes.get(offset..offset + Chunk::LENGTH_LENGTH + Chunk::TYPE_LENGTH)?;
+    let (length, code) = header.split_at(Chunk::LENGTH_LENGTH);
+
+    // NOTE: checked before parsing the type, as this runs at every offset while resyncing
+    let length = u32::from_be_bytes(length.try_into().ok()?);
+    if length > Chunk::MAX_LENGTH || !code.iter().all(u8::is_ascii_alphabetic) {
+        return None;
+    }
+
+    let code: [u8; Chunk::TYPE_LENGTH] = code.try_into().ok()?;
+    let chunk_type = ChunkType::try_from(code).ok()?;
+
+    Some((length, chunk_type))
+}
+
+/// The chunk at `offset` if the input holds all of it, whatever its crc
+fn complete_at(bytes: &[u8], offset: usize) -> Option<Candidate<'_>> {
+    let (length, chunk_type) = header_at(bytes, offset)?;
+
+    let start = offset + Chunk::LENGTH_LENGTH + Chunk::TYPE_LENGTH;
+    let end = start.checked_add(length as usize)?;
+    let data = bytes.get(start..end)?;
+    let stored = u32::from_be_bytes(bytes.get(end..end + Chunk::CRC_LENGTH)?.try_into().ok()?);
+
+    Some(Candidate {
+        chunk_type,
+        data,
+        stored,
+        next: end + Chunk::CRC_LENGTH,
+    })
+}
+
+/// Reads the chunk at `offset` if it is plausible, nothing is copied until it is recovered.
+///
+/// A chunk with a wrong crc is only accepted when it ends at another plausible chunk
+/// or at the end of the input, otherwise its length is probably what is damaged.
+/// While resyncing, chunks with a wrong crc also need to be followed by a valid one.
+fn chunk_at(bytes: &[u8], offset: usize, resync: bool) -> Option<Candidate<'_>> {
+    let candidate = complete_at(bytes, offset)?;
+
+    if !candidate.is_valid() {
+        let next = candidate.next;
+        let followed = next == bytes.len()
+            || if resync {
+                complete_at(bytes, next).is_some_and(|candidate| candidate.is_valid())
+            } else {
+                header_at(bytes, next).is_some()
+            };
+
+        if !followed {
+            return None;
+        }
+    }
+
+    Some(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::png::chunk::chunk_ref::ChunkRef;
+    use crate::png::limits::LimitError;
+    use crate::png::png_ref::PngRef;
+    use crate::png::tests::PNG_FILE;
+
+    fn offset_of(code: &str) -> usize {
+        let png = PngRef::try_from(PNG_FILE.as_ref()).unwrap();
+
+        Png::STANDARD_HEADER.len()
+            + png
+                .chunks()
+                .iter()
+                .take_while(|chunk| chunk.chunk_type().to_string() != code)
+                .map(ChunkRef::total_length)
+                .sum::<usize>()
+    }
+
+    fn codes(salvage: &Salvage) -> Vec<String> {
+        salvage
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk().chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_salvage_valid_file() {
+        let salvage = Png::salvage(&PNG_FILE).unwrap();
+
+        assert!(salvage.is_clean());
+        assert_eq!(salvage.into_png().as_bytes(), PNG_FILE);
+    }
+
+    #[test]
+    fn test_salvage_crc_mismatch() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[offset_of("RuSt") + 8] ^= 0xFF;
+
+        let salvage = Png::salvage(&bytes).unwrap();
+        let chunk = &salvage.chunks()[5];
+
+        assert_eq!(chunk.chunk().chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.offset(), offset_of("RuSt"));
+        assert_eq!(chunk.status(), ChunkStatus::CrcMismatch);
+        assert!(matches!(
+            salvage.diagnostics(),
+            [Diagnostic::CrcMismatch { offset, .. }] if *offset == offset_of("RuSt")
+        ));
+        assert_eq!(salvage.chunks().len(), 7);
+    }
+
+    #[test]
+    fn test_salvage_garbage_between_chunks() {
+        let offset = offset_of("IDAT");
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.splice(offset..offset, [0xAB; 37]);
+
+        let salvage = Png::salvage(&bytes).unwrap();
+
+        assert_eq!(
+            salvage.diagnostics(),
+            [Diagnostic::Garbage { offset, length: 37 }]
+        );
+        assert_eq!(salvage.into_png().as_bytes(), PNG_FILE);
+    }
+
+    #[test]
+    fn test_salvage_corrupted_length() {
+        let offset = offset_of("pHYs");
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[offset..offset + 4].copy_from_slice(&0x00FF_FFFF_u32.to_be_bytes());
+
+        let salvage = Png::salvage(&bytes).unwrap();
+
+        assert!(matches!(
+            salvage.diagnostics(),
+            [Diagnostic::Garbage { offset: garbage, .. }] if *garbage == offset
+        ));
+        assert_eq!(
+            codes(&salvage),
+            ["IHDR", "sRGB", "gAMA", "IDAT", "RuSt", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_salvage_truncated_file() {
+        let offset = offset_of("IDAT");
+        let salvage = Png::salvage(&PNG_FILE[..offset + 100]).unwrap();
+
+        let chunk = salvage.chunks().last().unwrap();
+
+        assert_eq!(codes(&salvage), ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT"]);
+        assert_eq!(chunk.status(), ChunkStatus::Truncated);
+        assert_eq!(chunk.chunk().data().len(), 92);
+        assert!(matches!(
+            salvage.diagnostics(),
+            [Diagnostic::Truncated { offset: truncated, .. }] if *truncated == offset
+        ));
+    }
+
+    #[test]
+    fn test_salvage_damaged_header() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[1] = b'X';
+
+        let salvage = Png::salvage(&bytes).unwrap();
+
+        assert_eq!(
+            salvage.diagnostics(),
+            [
+                Diagnostic::InvalidHeader,
+                Diagnostic::Garbage {
+                    offset: 0,
+                    length: 8
+                }
+            ]
+        );
+        assert_eq!(salvage.chunks().len(), 7);
+    }
+
+    #[test]
+    fn test_salvage_chunks_after_iend() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend(Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec()).as_bytes());
+
+        let salvage = Png::salvage(&bytes).unwrap();
+
+        assert!(salvage.is_clean());
+        assert_eq!(codes(&salvage).last().unwrap(), "ruSt");
+    }
+
+    #[test]
+    fn test_salvage_damaged_last_chunk_with_trailing_bytes() {
+        let mut bytes = PNG_FILE.to_vec();
+        let crc = bytes.len() - 1;
+        bytes[crc] ^= 0xFF;
+        bytes.extend(b"xyz");
+
+        let salvage = Png::salvage(&bytes).unwrap();
+        let chunk = salvage.chunks().last().unwrap();
+
+        assert_eq!(chunk.chunk().chunk_type().to_string(), "IEND");
+        assert_eq!(chunk.status(), ChunkStatus::CrcMismatch);
+        assert!(chunk.chunk().data().is_empty());
+        assert!(matches!(
+            salvage.diagnostics(),
+            [
+                Diagnostic::CrcMismatch { .. },
+                Diagnostic::Garbage { length: 3, .. }
+            ]
+        ));
+        assert_eq!(salvage.into_png().as_bytes(), PNG_FILE);
+    }
+
+    #[test]
+    fn test_salvage_limits() {
+        let limits = Limits {
+            max_chunk_count: 3,
+            ..Limits::default()
+        };
+        let error = Png::salvage_with_limits(&PNG_FILE, limits).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::TooManyChunks(3))
+        ));
+
+        let limits = Limits {
+            max_chunk_length: 100,
+            ..Limits::default()
+        };
+        let error = Png::salvage_with_limits(&PNG_FILE, limits).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::ChunkTooLong(_, 100))
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::{Signature, Signer};
+use sha2::{Digest, Sha256};
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::recipients::parse_key;
+use super::{Png, PngError};
+
+const SIGNING_PREFIX: &str = "pngrs-signing-";
+const VERIFYING_PREFIX: &str = "pngrs-verify-";
+/// Prepended to the signed bytes so the signatures can not be reused elsewhere
+const CONTEXT: &[u8] = b"pngrs message signature";
+
+pub const VERSION: u8 = 1;
+const BOUND_TO_IMAGE: u8 = 1;
+
+const KEY_LENGTH: usize = 32;
+const HASH_LENGTH: usize = 32;
+const SIGNATURE_LENGTH: usize = 64;
+
+/// Ed25519 secret key used to sign messages
+#[derive(Clone)]
+pub struct SigningKey {
+    key: ed25519_dalek::SigningKey,
+}
+
+/// Ed25519 public key of the signer of a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+    key: ed25519_dalek::VerifyingKey,
+}
+
+/// Chunks covered by [`Png::image_hash`], every one that changes the decoded pixels
+const PIXEL_CHUNKS: [ChunkType; 7] = [
+    ChunkType::IHDR,
+    ChunkType::PLTE,
+    ChunkType::TRNS,
+    ChunkType::IDAT,
+    ChunkType::ACTL,
+    ChunkType::FCTL,
+    ChunkType::FDAT,
+];
+
+/// Signature of a message stored in a companion `sgNt` chunk.
+///
+/// It covers the code and data of the message chunk and, when bound to the image, a hash of the
+/// chunks that make up its pixels so changes to them after signing can be detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageSignature {
+    code: ChunkType,
+    key: VerifyingKey,
+    image_hash: Option<[u8; HASH_LENGTH]>,
+    signature: Signature,
+}
+
+/// Result of checking one signature of an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub signature: MessageSignature,
+    /// Position among the messages with the signed code of the one the signature is valid for
+    pub message: Option<usize>,
+    /// Whether the chunks that make up the pixels are unchanged, `None` when not bound to the image
+    pub image_unchanged: Option<bool>,
+}
+
+#[derive(Debug, ThisError)]
+pub enum SignatureError {
+    #[error("Error parsing sgNt chunk, version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Error parsing sgNt chunk, expected {0} bytes of data but found {1}")]
+    InvalidLength(usize, usize),
+    #[error("Error parsing sgNt chunk, the public key is not a valid Ed25519 key")]
+    InvalidKey,
+}
+
+impl SigningKey {
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut secret = [0; KEY_LENGTH];
+        OsRng.fill_bytes(&mut secret);
+
+        Self {
+            key: ed25519_dalek::SigningKey::from_bytes(&secret),
+        }
+    }
+
+    #[must_use]
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            key: self.key.verifying_key(),
+        }
+    }
+}
+
+impl FromStr for SigningKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self {
+            key: ed25519_dalek::SigningKey::from_bytes(&parse_key(s, SIGNING_PREFIX)?),
+        })
+    }
+}
+
+impl Display for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{SIGNING_PREFIX}{}", hex::encode(self.key.as_bytes()))
+    }
+}
+
+impl VerifyingKey {
+    /// Short SHA-256 based identifier of the key, to compare with the one of a trusted signer
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        let hash = Sha256::digest(self.key.as_bytes());
+
+        hash[..16]
+            .chunks(2)
+            .map(hex::encode)
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    fn from_bytes(bytes: &[u8; KEY_LENGTH]) -> Result<Self> {
+        match ed25519_dalek::VerifyingKey::from_bytes(bytes) {
+            Ok(key) => Ok(Self { key }),
+            Err(_) => bail!(SignatureError::InvalidKey),
+        }
+    }
+}
+
+impl FromStr for VerifyingKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_bytes(&parse_key(s, VERIFYING_PREFIX)?)
+    }
+}
+
+impl Display for VerifyingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{VERIFYING_PREFIX}{}", hex::encode(self.key.as_bytes()))
+    }
+}
+
+impl MessageSignature {
+    /// Signs the data of the `message` chunk, binding it to the image if `image_hash` is given
+    #[must_use]
+    pub fn sign(key: &SigningKey, message: &Chunk, image_hash: Option<[u8; HASH_LENGTH]>) -> Self {
+        let mut signature = Self {
+            code: message.chunk_type().clone(),
+            key: key.verifying_key(),
+            image_hash,
+            signature: Signature::from_bytes(&[0; SIGNATURE_LENGTH]),
+        };
+        signature.signature = key.key.sign(&signature.signed_bytes(message.data()));
+
+        signature
+    }
+
+    /// Whether this is a valid signature of the data of a message chunk
+    #[must_use]
+    pub fn verify(&self, message: &Chunk) -> bool {
+        *message.chunk_type() == self.code
+            && self
+                .key
+                .key
+                .verify_strict(&self.signed_bytes(message.data()), &self.signature)
+                .is_ok()
+    }
+
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let Some((&version, data)) = data.split_first() else {
+            bail!(SignatureError::InvalidLength(2, 0))
+        };
+        if version != VERSION {
+            bail!(SignatureError::UnsupportedVersion(version))
+        }
+
+        let bound = data
+            .first()
+            .is_some_and(|flags| flags & BOUND_TO_IMAGE != 0);
+        let expected = 1 + 4 + KEY_LENGTH + if bound { HASH_LENGTH } else { 0 } + SIGNATURE_LENGTH;
+        if data.len() != expected {
+            bail!(SignatureError::InvalidLength(expected + 1, data.len() + 1))
+        }
+
+        let (code, data) = data[1..].split_at(4);
+        let (key, data) = data.split_at(KEY_LENGTH);
+        let (image_hash, signature) = data.split_at(data.len() - SIGNATURE_LENGTH);
+
+        Ok(Self {
+            code: ChunkType::try_from(<[u8; 4]>::try_from(code)?)?,
+            key: VerifyingKey::from_bytes(key.try_into()?)?,
+            image_hash: bound.then(|| image_hash.try_into()).transpose()?,
+            signature: Signature::from_bytes(signature.try_into()?),
+        })
+    }
+
+    #[must_use]
+    pub fn to_chunk(&self) -> Chunk {
+        let mut data = self.header();
+        data.extend(self.signature.to_bytes());
+
+        Chunk::new(ChunkType::SGNT, data)
+    }
+
+    #[must_use]
+    pub const fn code(&self) -> &ChunkType {
+        &self.code
+    }
+
+    #[must_use]
+    pub const fn key(&self) -> &VerifyingKey {
+        &self.key
+    }
+
+    #[must_use]
+    pub const fn image_hash(&self) -> Option<&[u8; HASH_LENGTH]> {
+        self.image_hash.as_ref()
+    }
+
+    /// Chunk data before the signature
+    fn header(&self) -> Vec<u8> {
+        let flags = if self.image_hash.is_some() {
+            BOUND_TO_IMAGE
+        } else {
+            0
+        };
+
+        let mut data = vec![VERSION, flags];
+        data.extend(self.code.bytes());
+        data.extend(self.key.key.as_bytes());
+        data.extend(self.image_hash.iter().flatten());
+
+        data
+    }
+
+    fn signed_bytes(&self, message: &[u8]) -> Vec<u8> {
+        [CONTEXT, &self.header(), message].concat()
+    }
+}
+
+impl Png {
+    /// SHA-256 of the chunks that together make up the pixels, each framed by its type and
+    /// length: the header, palette, transparency, image data and animation frames
+    #[must_use]
+    pub fn image_hash(&self) -> [u8; HASH_LENGTH] {
+        let mut hasher = Sha256::new();
+
+        for chunk in self
+            .chunks
+            .iter()
+            .filter(|chunk| PIXEL_CHUNKS.contains(chunk.chunk_type()))
+        {
+            hasher.update(chunk.chunk_type().bytes());
+            hasher.update(chunk.length().to_be_bytes());
+            hasher.update(chunk.data());
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// Signs the message with code `chunk_type` at position `n` among the ones with that code,
+    /// storing the signature in a `sgNt` chunk right after it
+    pub fn sign_message(
+        &mut self,
+        chunk_type: &str,
+        n: usize,
+        key: &SigningKey,
+        bind_to_image: bool,
+    ) -> Result<MessageSignature> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+
+        let Some(index) = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| *chunk.chunk_type() == chunk_type)
+            .nth(n)
+            .map(|(i, _)| i)
+        else {
+            bail!(PngError::NoChunkTypeFound(chunk_type.to_string()));
+        };
+
+        let image_hash = bind_to_image.then(|| self.image_hash());
+        let signature = MessageSignature::sign(key, &self.chunks[index], image_hash);
+
+        self.chunks.insert(index + 1, signature.to_chunk());
+
+        Ok(signature)
+    }
+
+    /// Checks every `sgNt` chunk against the messages with the signed code.
+    ///
+    /// Each chunk gets its own result, so a malformed one does not hide the others.
+    #[must_use]
+    pub fn verify_signatures(&self) -> Vec<Result<Verification>> {
+        let image_hash = self.image_hash();
+
+        self.chunks
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == ChunkType::SGNT)
+            .map(|chunk| {
+                let signature = MessageSignature::from_data(chunk.data())?;
+
+                let message = self
+                    .chunks
+                    .iter()
+                    .filter(|chunk| chunk.chunk_type() == signature.code())
+                    .position(|message| signature.verify(message));
+                let image_unchanged = signature.image_hash().map(|hash| *hash == image_hash);
+
+                Ok(Verification {
+                    signature,
+                    message,
+                    image_unchanged,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_key_round_trip() {
+        let key = SigningKey::generate();
+
+        let parsed = SigningKey::from_str(&key.to_string()).unwrap();
+        assert_eq!(parsed.verifying_key(), key.verifying_key());
+
+        let verifying = VerifyingKey::from_str(&key.verifying_key().to_string()).unwrap();
+        assert_eq!(verifying, key.verifying_key());
+        assert_eq!(verifying.fingerprint().len(), 39);
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let key = SigningKey::generate();
+
+        let signature = png.sign_message("RuSt", 0, &key, true).unwrap();
+        assert_eq!(png.chunks()[6].chunk_type(), &ChunkType::SGNT);
+
+        let verifications = png.verify_signatures();
+        assert_eq!(verifications.len(), 1);
+        let verification = verifications[0].as_ref().unwrap();
+        assert_eq!(verification.signature, signature);
+        assert_eq!(verification.message, Some(0));
+        assert_eq!(verification.image_unchanged, Some(true));
+    }
+
+    #[test]
+    fn test_unbound_signature() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        png.sign_message("RuSt", 0, &SigningKey::generate(), false)
+            .unwrap();
+
+        let verification = png.verify_signatures().remove(0).unwrap();
+        assert_eq!(verification.message, Some(0));
+        assert_eq!(verification.image_unchanged, None);
+    }
+
+    #[test]
+    fn test_tampered() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.sign_message("RuSt", 0, &SigningKey::generate(), true)
+            .unwrap();
+
+        // NOTE: changing the pixels keeps the message valid but is reported
+        let idat = png.remove_chunk("IDAT").unwrap();
+        let mut data = idat.data().to_vec();
+        data[10] ^= 1;
+        png.insert_chunk(
+            Chunk::new(ChunkType::IDAT, data),
+            crate::png::ChunkPlacement::BeforeFirstIdat,
+        );
+
+        let verification = png.verify_signatures().remove(0).unwrap();
+        assert_eq!(verification.message, Some(0));
+        assert_eq!(verification.image_unchanged, Some(false));
+
+        png.remove_chunk("RuSt").unwrap();
+        png.insert_chunk(
+            Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"bye".to_vec()),
+            crate::png::ChunkPlacement::BeforeIend,
+        );
+
+        let verification = png.verify_signatures().remove(0).unwrap();
+        assert_eq!(verification.message, None);
+    }
+
+    #[test]
+    fn test_image_hash_coverage() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let hash = png.image_hash();
+
+        for chunk_type in [ChunkType::PLTE, ChunkType::TRNS, ChunkType::FDAT] {
+            let mut changed = Png::try_from(&PNG_FILE[..]).unwrap();
+            changed.insert_chunk(
+                Chunk::new(chunk_type, vec![0, 0, 0, 0]),
+                crate::png::ChunkPlacement::AfterLastIdat,
+            );
+            assert_ne!(changed.image_hash(), hash);
+        }
+
+        // NOTE: chunks are framed, so moving bytes from one IDAT to the next changes the hash
+        let split = |at: usize| {
+            let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+            let data = png.remove_chunk("IDAT").unwrap().data().to_vec();
+            let (first, second) = data.split_at(at);
+            for part in [second, first] {
+                png.insert_chunk(
+                    Chunk::new(ChunkType::IDAT, part.to_vec()),
+                    crate::png::ChunkPlacement::BeforeFirstIdat,
+                );
+            }
+            png.image_hash()
+        };
+        assert_ne!(split(10), split(11));
+    }
+
+    #[test]
+    fn test_malformed_signature_chunk() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.sign_message("RuSt", 0, &SigningKey::generate(), false)
+            .unwrap();
+        png.insert_chunk(
+            Chunk::new(ChunkType::SGNT, vec![VERSION, 0, 1, 2]),
+            crate::png::ChunkPlacement::AfterIhdr,
+        );
+
+        let verifications = png.verify_signatures();
+        assert_eq!(verifications.len(), 2);
+        assert!(matches!(
+            verifications[0].as_ref().unwrap_err().downcast_ref(),
+            Some(SignatureError::InvalidLength(..))
+        ));
+        assert_eq!(verifications[1].as_ref().unwrap().message, Some(0));
+    }
+
+    #[test]
+    fn test_chunk_round_trip() {
+        let message = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hey".to_vec());
+        let signature = MessageSignature::sign(&SigningKey::generate(), &message, None);
+
+        let chunk = signature.to_chunk();
+        let parsed = MessageSignature::from_data(chunk.data()).unwrap();
+        assert_eq!(parsed, signature);
+        assert!(parsed.verify(&message));
+
+        let error = MessageSignature::from_data(&chunk.data()[..50]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(SignatureError::InvalidLength(..))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::Png;
+
+/// Critical chunks defined by the specification, the only ones always kept
+const IMAGE_CHUNKS: [ChunkType; 4] = [
+    ChunkType::IHDR,
+    ChunkType::PLTE,
+    ChunkType::IDAT,
+    ChunkType::IEND,
+];
+
+/// Ancillary chunks that change how the image is displayed, kept when stripping metadata
+pub const RENDERING_CHUNKS: [ChunkType; 14] = [
+    ChunkType::TRNS,
+    ChunkType::GAMA,
+    ChunkType::CHRM,
+    ChunkType::SRGB,
+    ChunkType::ICCP,
+    ChunkType::CICP,
+    ChunkType::MDCV,
+    ChunkType::CLLI,
+    ChunkType::BKGD,
+    ChunkType::SBIT,
+    ChunkType::PHYS,
+    ChunkType::ACTL,
+    ChunkType::FCTL,
+    ChunkType::FDAT,
+];
+
+/// Which chunks survive [`Png::strip`] besides the image and rendering chunks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripOptions {
+    /// Ancillary chunks kept on top of the rendering chunks
+    pub allow: HashSet<ChunkType>,
+    /// Ancillary chunks dropped even if they are rendering chunks or allowed
+    pub deny: HashSet<ChunkType>,
+    /// Keep private and unknown critical chunks, the ones used to hide messages
+    pub keep_messages: bool,
+}
+
+impl StripOptions {
+    #[must_use]
+    pub fn keeps(&self, chunk_type: &ChunkType) -> bool {
+        if IMAGE_CHUNKS.contains(chunk_type) {
+            return true;
+        }
+
+        if self.deny.contains(chunk_type) {
+            return false;
+        }
+
+        RENDERING_CHUNKS.contains(chunk_type)
+            || self.allow.contains(chunk_type)
+            || (self.keep_messages && (chunk_type.is_critical() || !chunk_type.is_public()))
+    }
+}
+
+impl Png {
+    /// Removes every chunk not needed to render the image, such as timestamps, text or EXIF data,
+    /// along with any trailing data. Returns the dropped chunks.
+    ///
+    /// `IHDR`, `PLTE`, `IDAT` and `IEND` are always kept, as the image can not be decoded without
+    /// them. Other critical chunks are treated as messages.
+    pub fn strip(&mut self, options: &StripOptions) -> Vec<Chunk> {
+        let (kept, dropped) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| options.keeps(chunk.chunk_type()));
+
+        self.chunks = kept;
+        self.trailing_data.clear();
+
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    fn png_with(codes: &[&str]) -> Png {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        for code in codes {
+            png.append_chunk(Chunk::new(ChunkType::from_str(code).unwrap(), Vec::new()));
+        }
+        png.set_trailing_data(b"trailing".to_vec());
+
+        png
+    }
+
+    fn codes(chunks: &[Chunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_strip_default() {
+        let mut png = png_with(&["tIME", "bKGD", "tEXt", "cLLI", "mDCV", "ruSt"]);
+
+        let dropped = png.strip(&StripOptions::default());
+
+        assert_eq!(codes(&dropped), ["RuSt", "tIME", "tEXt", "ruSt"]);
+        assert_eq!(
+            codes(png.chunks()),
+            ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "IEND", "bKGD", "cLLI", "mDCV"]
+        );
+        assert!(png.trailing_data().is_empty());
+    }
+
+    #[test]
+    fn test_strip_allow_deny_and_messages() {
+        let mut png = png_with(&["tIME", "tEXt", "ruSt"]);
+
+        let options = StripOptions {
+            allow: HashSet::from([ChunkType::TEXT]),
+            deny: HashSet::from([ChunkType::PHYS, ChunkType::IDAT]),
+            keep_messages: true,
+        };
+        let dropped = png.strip(&options);
+
+        assert_eq!(codes(&dropped), ["pHYs", "tIME"]);
+        assert!(png.chunk_by_type("tEXt").is_some());
+        assert!(png.chunk_by_type("ruSt").is_some());
+        assert!(png.chunk_by_type("RuSt").is_some());
+        assert!(png.chunk_by_type("IDAT").is_some());
+    }
+}
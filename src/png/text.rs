@@ -0,0 +1,454 @@
+use anyhow::{bail, Error, Result};
+use flate2::Compression;
+use std::fmt::Display;
+use thiserror::Error as ThisError;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::limits::Limits;
+use super::{zlib, ChunkPlacement, Png, PngError};
+
+/// A typed `tEXt`, `zTXt` or `iTXt` chunk, a keyword with its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    keyword: String,
+    text: String,
+    kind: TextKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextKind {
+    /// `tEXt`, uncompressed Latin-1 text
+    Text,
+    /// `zTXt`, zlib compressed Latin-1 text
+    Compressed,
+    /// `iTXt`, UTF-8 text that can optionally be compressed
+    International {
+        compressed: bool,
+        language_tag: String,
+        translated_keyword: String,
+    },
+}
+
+#[derive(Debug, ThisError)]
+pub enum TextError {
+    #[error("Invalid text keyword {0:?}, it must be 1 to 79 printable Latin-1 characters without leading, trailing or consecutive spaces")]
+    InvalidKeyword(String),
+    #[error("Invalid language tag {0:?}, it must only contain ASCII letters, digits and hyphens")]
+    InvalidLanguageTag(String),
+    #[error("Text can not be stored as Latin-1, use an international text chunk instead")]
+    NonLatin1Text,
+    #[error("Text and translated keywords can not contain null characters")]
+    NullCharacter,
+    #[error("Error parsing text chunk, missing null separator")]
+    MissingNullSeparator,
+    #[error("Error parsing text chunk, unknown compression flag {0}")]
+    InvalidCompressionFlag(u8),
+    #[error("Error parsing text chunk, unknown compression method {0}")]
+    InvalidCompressionMethod(u8),
+    #[error("Chunk {0} is not a text chunk")]
+    NotATextChunk(String),
+}
+
+impl TextChunk {
+    pub const MAX_KEYWORD_LENGTH: usize = 79;
+
+    /// Creates an uncompressed `tEXt` chunk
+    pub fn new(keyword: &str, text: &str) -> Result<Self> {
+        Self::with_kind(keyword, text, TextKind::Text)
+    }
+
+    /// Creates a zlib compressed `zTXt` chunk
+    pub fn compressed(keyword: &str, text: &str) -> Result<Self> {
+        Self::with_kind(keyword, text, TextKind::Compressed)
+    }
+
+    /// Creates an `iTXt` chunk, which can hold any UTF-8 text
+    pub fn international(
+        keyword: &str,
+        text: &str,
+        language_tag: &str,
+        translated_keyword: &str,
+        compressed: bool,
+    ) -> Result<Self> {
+        let kind = TextKind::International {
+            compressed,
+            language_tag: language_tag.to_string(),
+            translated_keyword: translated_keyword.to_string(),
+        };
+
+        Self::with_kind(keyword, text, kind)
+    }
+
+    fn with_kind(keyword: &str, text: &str, kind: TextKind) -> Result<Self> {
+        validate_keyword(keyword)?;
+        if text.contains('\0') {
+            bail!(TextError::NullCharacter);
+        }
+
+        match &kind {
+            TextKind::Text | TextKind::Compressed => {
+                latin1_encode(text)?;
+            }
+            TextKind::International {
+                language_tag,
+                translated_keyword,
+                ..
+            } => {
+                validate_language_tag(language_tag)?;
+                if translated_keyword.contains('\0') {
+                    bail!(TextError::NullCharacter);
+                }
+            }
+        }
+
+        Ok(Self {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+            kind,
+        })
+    }
+
+    #[must_use]
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &TextKind {
+        &self.kind
+    }
+
+    #[must_use]
+    pub fn is_text_chunk(chunk_type: &ChunkType) -> bool {
+        [ChunkType::TEXT, ChunkType::ZTXT, ChunkType::ITXT].contains(chunk_type)
+    }
+
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        let mut data = latin1_encode(&self.keyword)?;
+        data.push(0);
+
+        let chunk_type = match &self.kind {
+            TextKind::Text => {
+                data.extend(latin1_encode(&self.text)?);
+                ChunkType::TEXT
+            }
+            TextKind::Compressed => {
+                data.push(0);
+                data.extend(zlib::compress(
+                    &latin1_encode(&self.text)?,
+                    Compression::default(),
+                )?);
+                ChunkType::ZTXT
+            }
+            TextKind::International {
+                compressed,
+                language_tag,
+                translated_keyword,
+            } => {
+                data.extend([u8::from(*compressed), 0]);
+                data.extend(language_tag.as_bytes());
+                data.push(0);
+                data.extend(translated_keyword.as_bytes());
+                data.push(0);
+                if *compressed {
+                    data.extend(zlib::compress(
+                        self.text.as_bytes(),
+                        Compression::default(),
+                    )?);
+                } else {
+                    data.extend(self.text.as_bytes());
+                }
+                ChunkType::ITXT
+            }
+        };
+
+        Ok(Chunk::new(chunk_type, data))
+    }
+}
+
+impl TextChunk {
+    /// Parses a text chunk, inflating compressed text only up to the given limits
+    pub fn from_chunk(value: &Chunk, limits: &Limits) -> Result<Self> {
+        let chunk_type = value.chunk_type();
+        let (keyword, rest) = split_null(value.data())?;
+        let keyword = latin1_decode(keyword);
+
+        let (text, kind) = if *chunk_type == ChunkType::TEXT {
+            (latin1_decode(rest), TextKind::Text)
+        } else if *chunk_type == ChunkType::ZTXT {
+            let Some((&method, compressed)) = rest.split_first() else {
+                bail!(TextError::MissingNullSeparator);
+            };
+            if method != 0 {
+                bail!(TextError::InvalidCompressionMethod(method));
+            }
+            (
+                latin1_decode(&zlib::decompress(compressed, limits.max_decompressed_size)?),
+                TextKind::Compressed,
+            )
+        } else if *chunk_type == ChunkType::ITXT {
+            let [flag, method, rest @ ..] = rest else {
+                bail!(TextError::MissingNullSeparator);
+            };
+            let compressed = match flag {
+                0 => false,
+                1 => true,
+                _ => bail!(TextError::InvalidCompressionFlag(*flag)),
+            };
+            if compressed && *method != 0 {
+                bail!(TextError::InvalidCompressionMethod(*method));
+            }
+            let (language_tag, rest) = split_null(rest)?;
+            let (translated_keyword, text) = split_null(rest)?;
+            let text = if compressed {
+                String::from_utf8(zlib::decompress(text, limits.max_decompressed_size)?)?
+            } else {
+                std::str::from_utf8(text)?.to_string()
+            };
+            let kind = TextKind::International {
+                compressed,
+                language_tag: std::str::from_utf8(language_tag)?.to_string(),
+                translated_keyword: std::str::from_utf8(translated_keyword)?.to_string(),
+            };
+            (text, kind)
+        } else {
+            bail!(TextError::NotATextChunk(chunk_type.to_string()));
+        };
+
+        validate_keyword(&keyword)?;
+
+        Ok(Self {
+            keyword,
+            text,
+            kind,
+        })
+    }
+}
+
+impl TryFrom<&Chunk> for TextChunk {
+    type Error = Error;
+
+    fn try_from(value: &Chunk) -> Result<Self> {
+        Self::from_chunk(value, &Limits::unlimited())
+    }
+}
+
+impl Display for TextChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.keyword)?;
+
+        if let TextKind::International {
+            language_tag,
+            translated_keyword,
+            ..
+        } = &self.kind
+        {
+            let details: Vec<&str> = [language_tag, translated_keyword]
+                .into_iter()
+                .map(String::as_str)
+                .filter(|detail| !detail.is_empty())
+                .collect();
+
+            if !details.is_empty() {
+                write!(f, " ({})", details.join(", "))?;
+            }
+        }
+
+        write!(f, ": {}", self.text)
+    }
+}
+
+impl Png {
+    /// Iterates over every text chunk of the image, parsing each one
+    pub fn text_chunks(&self) -> impl Iterator<Item = Result<TextChunk>> + '_ {
+        self.chunks
+            .iter()
+            .filter(|chunk| TextChunk::is_text_chunk(chunk.chunk_type()))
+            .map(|chunk| TextChunk::from_chunk(chunk, &self.limits))
+    }
+
+    /// Finds the first text chunk with the given keyword
+    pub fn text_by_keyword(&self, keyword: &str) -> Result<Option<TextChunk>> {
+        let keyword = latin1_encode(keyword)?;
+
+        self.chunks
+            .iter()
+            .find(|chunk| has_keyword(chunk, &keyword))
+            .map(|chunk| TextChunk::from_chunk(chunk, &self.limits))
+            .transpose()
+    }
+
+    /// Replaces every text chunk with the same keyword, or adds it before `IEND` if there is none
+    pub fn set_text(&mut self, text: &TextChunk) -> Result<()> {
+        let chunk = text.to_chunk()?;
+        let keyword = latin1_encode(text.keyword())?;
+
+        let index = self
+            .chunks
+            .iter()
+            .position(|chunk| has_keyword(chunk, &keyword));
+
+        self.chunks.retain(|chunk| !has_keyword(chunk, &keyword));
+
+        // NOTE: every chunk before the first match is kept, so its index is still valid
+        match index {
+            Some(index) => self.chunks.insert(index, chunk),
+            None => self.insert_chunk(chunk, ChunkPlacement::BeforeIend),
+        }
+
+        Ok(())
+    }
+
+    /// Removes every text chunk with the given keyword, returning the first one
+    pub fn remove_text(&mut self, keyword: &str) -> Result<TextChunk> {
+        let Some(text) = self.text_by_keyword(keyword)? else {
+            bail!(PngError::NoTextKeywordFound(keyword.to_string()));
+        };
+
+        let keyword = latin1_encode(keyword)?;
+        self.chunks.retain(|chunk| !has_keyword(chunk, &keyword));
+
+        Ok(text)
+    }
+}
+
+fn has_keyword(chunk: &Chunk, keyword: &[u8]) -> bool {
+    TextChunk::is_text_chunk(chunk.chunk_type())
+        && split_null(chunk.data()).is_ok_and(|(k, _)| k == keyword)
+}
+
+fn split_null(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let Some(index) = data.iter().position(|&b| b == 0) else {
+        bail!(TextError::MissingNullSeparator);
+    };
+
+    Ok((&data[..index], &data[index + 1..]))
+}
+
+pub(super) fn validate_keyword(keyword: &str) -> Result<()> {
+    let valid_chars = keyword
+        .chars()
+        .all(|c| matches!(u32::from(c), 32..=126 | 161..=255));
+
+    if !valid_chars
+        || keyword.is_empty()
+        || keyword.chars().count() > TextChunk::MAX_KEYWORD_LENGTH
+        || keyword.starts_with(' ')
+        || keyword.ends_with(' ')
+        || keyword.contains("  ")
+    {
+        bail!(TextError::InvalidKeyword(keyword.to_string()));
+    }
+
+    Ok(())
+}
+
+fn validate_language_tag(language_tag: &str) -> Result<()> {
+    if !language_tag
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        bail!(TextError::InvalidLanguageTag(language_tag.to_string()));
+    }
+
+    Ok(())
+}
+
+pub(super) fn latin1_encode(text: &str) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| u8::try_from(c).map_err(|_| TextError::NonLatin1Text.into()))
+        .collect()
+}
+
+pub(super) fn latin1_decode(bytes: &[u8]) -> String {
+    bytes.iter().copied().map(char::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = TextChunk::new("Comment", "Häppy text").unwrap();
+        let chunk = text.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type(), &ChunkType::TEXT);
+        assert_eq!(chunk.data(), b"Comment\0H\xe4ppy text");
+        assert_eq!(TextChunk::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_compressed_text_round_trip() {
+        let text = TextChunk::compressed("Description", &"long text ".repeat(50)).unwrap();
+        let chunk = text.to_chunk().unwrap();
+
+        assert_eq!(chunk.chunk_type(), &ChunkType::ZTXT);
+        assert!(chunk.data().len() < 500);
+        assert_eq!(TextChunk::try_from(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_international_text_round_trip() {
+        for compressed in [false, true] {
+            let text = TextChunk::international("Title", "タイトル", "ja", "タイトル", compressed)
+                .unwrap();
+            let chunk = text.to_chunk().unwrap();
+
+            assert_eq!(chunk.chunk_type(), &ChunkType::ITXT);
+            assert_eq!(TextChunk::try_from(&chunk).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_keywords() {
+        for keyword in ["", " Title", "Title ", "Ti  tle", "Ti\ntle", "タイトル"] {
+            assert!(TextChunk::new(keyword, "text").is_err());
+        }
+        assert!(TextChunk::new(&"k".repeat(80), "text").is_err());
+        assert!(TextChunk::new(&"k".repeat(79), "text").is_ok());
+    }
+
+    #[test]
+    fn test_non_latin1_text() {
+        assert!(TextChunk::new("Title", "タイトル").is_err());
+        assert!(TextChunk::compressed("Title", "タイトル").is_err());
+    }
+
+    #[test]
+    fn test_null_characters() {
+        let error = TextChunk::new("Title", "hidden\0text").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(TextError::NullCharacter)
+        ));
+        assert!(TextChunk::international("Title", "text\0", "", "", false).is_err());
+        assert!(TextChunk::international("Title", "text", "", "Tit\0le", true).is_err());
+    }
+
+    #[test]
+    fn test_png_set_get_remove_text() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        png.set_text(&TextChunk::new("Comment", "first").unwrap())
+            .unwrap();
+        png.set_text(&TextChunk::compressed("Comment", "second").unwrap())
+            .unwrap();
+
+        let text = png.text_by_keyword("Comment").unwrap().unwrap();
+        assert_eq!(text.text(), "second");
+        assert_eq!(png.text_chunks().count(), 1);
+        assert_eq!(png.chunks().last().unwrap().chunk_type(), &ChunkType::IEND);
+
+        let removed = png.remove_text("Comment").unwrap();
+        assert_eq!(removed.text(), "second");
+        assert!(png.text_by_keyword("Comment").unwrap().is_none());
+        assert!(png.remove_text("Comment").is_err());
+    }
+}
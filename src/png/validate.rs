@@ -0,0 +1,523 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use super::chunk::{chunk_type::ChunkType, Chunk};
+use super::ihdr::{ColorType, Ihdr};
+use super::png_ref::PngRef;
+use super::Png;
+
+/// A broken ordering or multiplicity rule of the PNG specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    chunk_index: usize,
+    offset: usize,
+    kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    IhdrNotFirst,
+    InvalidIhdr(String),
+    MissingChunk(ChunkType),
+    DuplicateChunk(ChunkType),
+    ChunkAfterIend(ChunkType),
+    TrailingData(usize),
+    NonContiguousIdat,
+    MustPrecede(ChunkType, ChunkType),
+    MustFollow(ChunkType, ChunkType),
+    MissingPalette,
+    UnexpectedPalette,
+    ConflictingChunks(ChunkType, ChunkType),
+    UnknownCriticalChunk(ChunkType),
+    ReservedBitSet(ChunkType),
+}
+
+/// Every critical chunk defined by the specification
+const CRITICAL: [&[u8; 4]; 4] = [b"IHDR", b"PLTE", b"IDAT", b"IEND"];
+
+/// Chunks that may appear at most once
+const SINGLE: [&[u8; 4]; 13] = [
+    b"IHDR", b"PLTE", b"IEND", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"bKGD",
+    b"hIST", b"tRNS", b"pHYs",
+];
+
+/// Chunks that may appear at most once, but that are not limited to a position
+const SINGLE_ANYWHERE: [&[u8; 4]; 3] = [b"tIME", b"acTL", b"eXIf"];
+
+/// Chunks that must come before `PLTE` and `IDAT`
+const BEFORE_PLTE: [&[u8; 4]; 8] = [
+    b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"mDCV", b"cLLI",
+];
+
+/// Chunks that must come after `PLTE` and before `IDAT`
+const AFTER_PLTE: [&[u8; 4]; 3] = [b"tRNS", b"hIST", b"bKGD"];
+
+/// Chunks that must come before `IDAT`
+const BEFORE_IDAT: [&[u8; 4]; 5] = [b"PLTE", b"pHYs", b"sPLT", b"acTL", b"eXIf"];
+
+impl Violation {
+    #[must_use]
+    pub const fn chunk_index(&self) -> usize {
+        self.chunk_index
+    }
+
+    /// Offset in bytes of the chunk from the start of the file
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "chunk {} at offset {}: {}",
+            self.chunk_index, self.offset, self.kind
+        )
+    }
+}
+
+impl Display for ViolationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IhdrNotFirst => write!(f, "the first chunk must be IHDR"),
+            Self::InvalidIhdr(error) => write!(f, "invalid IHDR chunk, {error}"),
+            Self::MissingChunk(chunk_type) => write!(f, "missing {chunk_type} chunk"),
+            Self::DuplicateChunk(chunk_type) => {
+                write!(f, "{chunk_type} chunk can only appear once")
+            }
+            Self::ChunkAfterIend(chunk_type) => write!(f, "{chunk_type} chunk after IEND"),
+            Self::TrailingData(length) => write!(f, "{length} bytes of data after IEND"),
+            Self::NonContiguousIdat => write!(f, "IDAT chunks must be consecutive"),
+            Self::MustPrecede(chunk_type, other) => {
+                write!(f, "{chunk_type} chunk must come before {other}")
+            }
+            Self::MustFollow(chunk_type, other) => {
+                write!(f, "{chunk_type} chunk must come after {other}")
+            }
+            Self::MissingPalette => write!(f, "indexed images need a PLTE chunk"),
+            Self::UnexpectedPalette => write!(f, "grayscale images can not have a PLTE chunk"),
+            Self::ConflictingChunks(chunk_type, other) => {
+                write!(
+                    f,
+                    "{chunk_type} and {other} chunks should not both be present"
+                )
+            }
+            Self::UnknownCriticalChunk(chunk_type) => write!(
+                f,
+                "{chunk_type} chunk is critical but unknown, decoders will refuse the image"
+            ),
+            Self::ReservedBitSet(chunk_type) => {
+                write!(f, "{chunk_type} chunk has the reserved bit set")
+            }
+        }
+    }
+}
+
+impl Png {
+    /// Checks the ordering and multiplicity rules of the specification, reporting every violation
+    #[must_use]
+    pub fn validate(&self) -> Vec<Violation> {
+        validate(
+            self.chunks
+                .iter()
+                .map(|chunk| (chunk.chunk_type(), chunk.length(), chunk.data())),
+            self.trailing_data().len(),
+        )
+    }
+}
+
+impl PngRef<'_> {
+    /// Checks the ordering and multiplicity rules of the specification, reporting every violation
+    #[must_use]
+    pub fn validate(&self) -> Vec<Violation> {
+        validate(
+            self.chunks()
+                .iter()
+                .map(|chunk| (chunk.chunk_type(), chunk.length(), chunk.data())),
+            self.trailing_data().len(),
+        )
+    }
+}
+
+/// Chunk positions and types of the image being validated, with the violations found so far
+struct Validator<'a> {
+    chunks: Vec<(usize, &'a ChunkType, &'a [u8])>,
+    end_offset: usize,
+    violations: Vec<Violation>,
+}
+
+fn validate<'a>(
+    chunks: impl Iterator<Item = (&'a ChunkType, u32, &'a [u8])>,
+    trailing: usize,
+) -> Vec<Violation> {
+    let mut end_offset = Png::STANDARD_HEADER.len();
+    let chunks = chunks
+        .map(|(chunk_type, length, data)| {
+            let offset = end_offset;
+            end_offset += length as usize + Chunk::METEDATA_LENGTH;
+            (offset, chunk_type, data)
+        })
+        .collect();
+
+    let mut validator = Validator {
+        chunks,
+        end_offset,
+        violations: Vec::new(),
+    };
+
+    validator.check_header();
+    validator.check_chunks();
+    validator.check_required();
+
+    if trailing > 0 {
+        let index = validator.chunks.len();
+        validator.report(index, ViolationKind::TrailingData(trailing));
+    }
+
+    let mut violations = validator.violations;
+    violations.sort_by_key(|violation| violation.chunk_index);
+
+    violations
+}
+
+impl Validator<'_> {
+    fn report(&mut self, chunk_index: usize, kind: ViolationKind) {
+        let offset = self
+            .chunks
+            .get(chunk_index)
+            .map_or(self.end_offset, |chunk| chunk.0);
+
+        self.violations.push(Violation {
+            chunk_index,
+            offset,
+            kind,
+        });
+    }
+
+    fn position(&self, code: [u8; 4]) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|(_, chunk_type, _)| chunk_type.bytes() == code)
+    }
+
+    /// `IHDR` must be first, be valid and agree with the presence of `PLTE`
+    fn check_header(&mut self) {
+        let Some(&(_, chunk_type, data)) = self.chunks.first() else {
+            self.report(0, ViolationKind::MissingChunk(ChunkType::IHDR));
+            return;
+        };
+
+        if *chunk_type != ChunkType::IHDR {
+            if self.position(*b"IHDR").is_none() {
+                self.report(0, ViolationKind::MissingChunk(ChunkType::IHDR));
+            }
+            return;
+        }
+
+        match Ihdr::from_data(data) {
+            Ok(ihdr) => match (ihdr.color_type(), self.position(*b"PLTE")) {
+                (ColorType::Indexed, None) => self.report(0, ViolationKind::MissingPalette),
+                (ColorType::Grayscale | ColorType::GrayscaleAlpha, Some(index)) => {
+                    self.report(index, ViolationKind::UnexpectedPalette);
+                }
+                _ => {}
+            },
+            Err(error) => self.report(
+                0,
+                ViolationKind::InvalidIhdr(error.root_cause().to_string()),
+            ),
+        }
+    }
+
+    /// Multiplicity and position of every chunk relative to `PLTE`, `IDAT` and `IEND`
+    fn check_chunks(&mut self) {
+        let is = |chunk_type: &ChunkType, codes: &[&[u8; 4]]| {
+            codes.iter().any(|&&code| chunk_type.bytes() == code)
+        };
+
+        let plte = self.position(*b"PLTE");
+        let first_idat = self.position(*b"IDAT");
+        let iend = self.position(*b"IEND");
+        let after = |index: usize, other: Option<usize>| other.is_some_and(|other| index > other);
+
+        let mut seen = HashSet::new();
+        let mut idat_ended = false;
+
+        for index in 0..self.chunks.len() {
+            let chunk_type = self.chunks[index].1;
+
+            if *chunk_type == ChunkType::IHDR && index != 0 {
+                self.report(index, ViolationKind::IhdrNotFirst);
+            }
+
+            if !chunk_type.is_reserved_bit_valid() {
+                self.report(index, ViolationKind::ReservedBitSet(chunk_type.clone()));
+            }
+
+            if chunk_type.is_critical() && !is(chunk_type, &CRITICAL) {
+                self.report(
+                    index,
+                    ViolationKind::UnknownCriticalChunk(chunk_type.clone()),
+                );
+            }
+
+            if (is(chunk_type, &SINGLE) || is(chunk_type, &SINGLE_ANYWHERE))
+                && !seen.insert(chunk_type)
+            {
+                self.report(index, ViolationKind::DuplicateChunk(chunk_type.clone()));
+            }
+
+            if after(index, iend) {
+                self.report(index, ViolationKind::ChunkAfterIend(chunk_type.clone()));
+            }
+
+            if *chunk_type == ChunkType::IDAT {
+                if idat_ended {
+                    self.report(index, ViolationKind::NonContiguousIdat);
+                    idat_ended = false;
+                }
+            } else if after(index, first_idat) {
+                idat_ended = true;
+            }
+
+            if is(chunk_type, &BEFORE_PLTE) && after(index, plte) {
+                let kind = ViolationKind::MustPrecede(chunk_type.clone(), ChunkType::PLTE);
+                self.report(index, kind);
+            }
+
+            if is(chunk_type, &AFTER_PLTE) && plte.is_some_and(|plte| index < plte) {
+                let kind = ViolationKind::MustFollow(chunk_type.clone(), ChunkType::PLTE);
+                self.report(index, kind);
+            }
+
+            if (is(chunk_type, &BEFORE_PLTE)
+                || is(chunk_type, &AFTER_PLTE)
+                || is(chunk_type, &BEFORE_IDAT))
+                && after(index, first_idat)
+            {
+                let kind = ViolationKind::MustPrecede(chunk_type.clone(), ChunkType::IDAT);
+                self.report(index, kind);
+            }
+        }
+    }
+
+    /// Chunks every image needs and chunks that can not be used together
+    fn check_required(&mut self) {
+        if let (Some(iccp), Some(srgb)) = (self.position(*b"iCCP"), self.position(*b"sRGB")) {
+            let kind = ViolationKind::ConflictingChunks(
+                self.chunks[iccp].1.clone(),
+                self.chunks[srgb].1.clone(),
+            );
+            self.report(iccp.max(srgb), kind);
+        }
+
+        let iend = self.position(*b"IEND");
+
+        if self.position(*b"IDAT").is_none() {
+            let index = iend.unwrap_or(self.chunks.len());
+            self.report(index, ViolationKind::MissingChunk(ChunkType::IDAT));
+        }
+
+        if iend.is_none() {
+            self.report(
+                self.chunks.len(),
+                ViolationKind::MissingChunk(ChunkType::IEND),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::ihdr::InterlaceMethod;
+    use crate::png::tests::PNG_FILE;
+    use std::str::FromStr;
+
+    fn chunk(code: &str) -> Chunk {
+        Chunk::new(ChunkType::from_str(code).unwrap(), Vec::new())
+    }
+
+    fn ihdr(color_type: ColorType) -> Chunk {
+        Ihdr::new(1, 1, 8, color_type, InterlaceMethod::None)
+            .unwrap()
+            .to_chunk()
+    }
+
+    fn kinds(png: &Png) -> Vec<(usize, ViolationKind)> {
+        png.validate()
+            .into_iter()
+            .map(|violation| (violation.chunk_index(), violation.kind().clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_image_file() {
+        // NOTE: the image file only breaks the rules with its critical RuSt message chunk
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(
+            kinds(&png),
+            [(
+                5,
+                ViolationKind::UnknownCriticalChunk(ChunkType::from_str("RuSt").unwrap())
+            )]
+        );
+        assert_eq!(
+            PngRef::try_from(&PNG_FILE[..]).unwrap().validate(),
+            png.validate()
+        );
+
+        png.remove_chunk("RuSt").unwrap();
+        assert!(png.validate().is_empty());
+    }
+
+    #[test]
+    fn test_missing_chunks() {
+        let png = Png::from_chunks(vec![chunk("tEXt")]);
+
+        assert_eq!(
+            kinds(&png),
+            [
+                (0, ViolationKind::MissingChunk(ChunkType::IHDR)),
+                (1, ViolationKind::MissingChunk(ChunkType::IDAT)),
+                (1, ViolationKind::MissingChunk(ChunkType::IEND)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ordering_violations() {
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Indexed),
+            chunk("IDAT"),
+            chunk("tEXt"),
+            chunk("IDAT"),
+            chunk("PLTE"),
+            chunk("IHDR"),
+            chunk("IEND"),
+            chunk("tIME"),
+        ]);
+
+        assert_eq!(
+            kinds(&png),
+            [
+                (3, ViolationKind::NonContiguousIdat),
+                (
+                    4,
+                    ViolationKind::MustPrecede(ChunkType::PLTE, ChunkType::IDAT)
+                ),
+                (5, ViolationKind::IhdrNotFirst),
+                (5, ViolationKind::DuplicateChunk(ChunkType::IHDR)),
+                (
+                    7,
+                    ViolationKind::ChunkAfterIend(ChunkType::from_str("tIME").unwrap())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_palette_rules() {
+        let png = Png::from_chunks(vec![ihdr(ColorType::Indexed), chunk("IDAT"), chunk("IEND")]);
+        assert_eq!(kinds(&png), [(0, ViolationKind::MissingPalette)]);
+
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Grayscale),
+            chunk("PLTE"),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ]);
+        assert_eq!(kinds(&png), [(1, ViolationKind::UnexpectedPalette)]);
+
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Rgb),
+            chunk("tRNS"),
+            chunk("PLTE"),
+            chunk("gAMA"),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ]);
+        assert_eq!(
+            kinds(&png),
+            [
+                (
+                    1,
+                    ViolationKind::MustFollow(
+                        ChunkType::from_str("tRNS").unwrap(),
+                        ChunkType::PLTE
+                    )
+                ),
+                (
+                    3,
+                    ViolationKind::MustPrecede(
+                        ChunkType::from_str("gAMA").unwrap(),
+                        ChunkType::PLTE
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_critical_chunks() {
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Rgb),
+            chunk("RuSt"),
+            chunk("ruST"),
+            chunk("rust"),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ]);
+
+        assert_eq!(
+            kinds(&png),
+            [
+                (
+                    1,
+                    ViolationKind::UnknownCriticalChunk(ChunkType::from_str("RuSt").unwrap())
+                ),
+                (
+                    3,
+                    ViolationKind::ReservedBitSet(ChunkType::from_str("rust").unwrap())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_violation_offsets() {
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Rgb),
+            chunk("IDAT"),
+            chunk("IEND"),
+            chunk("IEND"),
+        ]);
+        let violations = png.validate();
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].chunk_index(), 3);
+        assert_eq!(violations[0].offset(), 8 + 25 + 12 + 12);
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut png = Png::from_chunks(vec![ihdr(ColorType::Rgb), chunk("IDAT"), chunk("IEND")]);
+        png.set_trailing_data(b"hidden".to_vec());
+        let violations = png.validate();
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].kind(), &ViolationKind::TrailingData(6));
+        assert_eq!(violations[0].chunk_index(), 3);
+        assert_eq!(violations[0].offset(), 8 + 25 + 12 + 12);
+
+        let bytes = png.as_bytes();
+        assert_eq!(PngRef::try_from(&bytes[..]).unwrap().validate(), violations);
+    }
+}
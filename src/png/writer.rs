@@ -0,0 +1,57 @@
+use anyhow::Result;
+use std::io::Write;
+
+use super::chunk::Chunk;
+use super::Png;
+
+/// Writes a PNG one chunk at a time to any [`Write`] destination.
+///
+/// The signature is written on creation, so chunks can be streamed straight to a file
+/// or socket without building the whole image in memory first.
+pub struct PngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PngWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&Png::STANDARD_HEADER)?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+        chunk.write_to(&mut self.writer)
+    }
+
+    /// Flushes the underlying writer and gives it back
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::tests::PNG_FILE;
+
+    #[test]
+    fn test_writer_round_trip() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        for chunk in png.chunks() {
+            writer.write_chunk(chunk).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+
+        assert_eq!(bytes, PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_writer_empty() {
+        let bytes = PngWriter::new(Vec::new()).unwrap().finish().unwrap();
+        assert_eq!(bytes, Png::STANDARD_HEADER.to_vec());
+    }
+}
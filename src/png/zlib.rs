@@ -0,0 +1,60 @@
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use super::limits::LimitError;
+
+/// Compresses `data` into a zlib stream, the only compression method defined by PNG
+pub fn compress(data: &[u8], level: Compression) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), level);
+    encoder.write_all(data)?;
+
+    Ok(encoder.finish()?)
+}
+
+/// Inflates a zlib stream, failing once the output grows past `limit` bytes
+pub fn decompress(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(data)
+        .take(u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1))
+        .read_to_end(&mut decompressed)?;
+
+    if decompressed.len() > limit {
+        bail!(LimitError::DecompressedTooLarge(limit));
+    }
+
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zlib_round_trip() {
+        let data = b"This is where your secret message will be!".repeat(10);
+        let compressed = compress(&data, Compression::default()).unwrap();
+
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn test_zlib_invalid_stream() {
+        assert!(decompress(b"not a zlib stream", usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_zlib_limit() {
+        let data = vec![0; 1 << 20];
+        let compressed = compress(&data, Compression::best()).unwrap();
+
+        let error = decompress(&compressed, data.len() - 1).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(LimitError::DecompressedTooLarge(_))
+        ));
+    }
+}